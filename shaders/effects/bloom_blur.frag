@@ -0,0 +1,19 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+// one of the axes, the blur is done horizontally and vertically in separate passes
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+	vec2 offset = direction * texelSize;
+	vec3 sum = texture(scene, TexCoords).rgb * weights[0];
+	for (int i = 1; i < 5; i++)
+	{
+		sum += texture(scene, TexCoords + offset * i).rgb * weights[i];
+		sum += texture(scene, TexCoords - offset * i).rgb * weights[i];
+	}
+	color = vec4(sum, 1.0);
+}
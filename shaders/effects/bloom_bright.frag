@@ -0,0 +1,17 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+// brightness above which the scene starts to glow
+uniform float threshold;
+
+void main()
+{
+	vec3 scene_color = texture(scene, TexCoords).rgb;
+	float brightness = max(scene_color.r, max(scene_color.g, scene_color.b));
+	// soft knee, so colors start glowing gradually instead of all at once at the threshold
+	float knee = threshold * 0.5 + 0.0001;
+	float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+	soft = soft * soft / (4.0 * knee);
+	float contribution = max(soft, brightness - threshold) / max(brightness, 0.0001);
+	color = vec4(scene_color * contribution, 1.0);
+}
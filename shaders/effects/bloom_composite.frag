@@ -0,0 +1,11 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+// the blurred bright parts of the scene
+uniform sampler2D bloom;
+
+void main()
+{
+	vec3 glow = texture(bloom, TexCoords).rgb * intensity;
+	color = vec4(texture(scene, TexCoords).rgb + glow, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+uniform vec2 offsets[KERNEL_SIZE];
+uniform float kernel[KERNEL_SIZE];
+// how far the scene swirls around
+uniform float strength;
+
+void main()
+{
+	vec2 coords = TexCoords + vec2(sin(time), cos(time)) * strength;
+	color = blend(vec4(convolve(coords, offsets, kernel), 1.0f));
+}
@@ -0,0 +1,7 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+void main()
+{
+	color = blend(vec4(1.0 - texture(scene, 1.0 - TexCoords).rgb, 1.0));
+}
@@ -0,0 +1,47 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+const float PI = 3.14159265;
+
+// strength of every part of the filter, 0 turns a part off
+uniform float scanlines;
+uniform float curvature;
+// in pixels
+uniform float aberration;
+uniform float phosphorMask;
+uniform float vignette;
+
+void main()
+{
+	// barrel distortion, bulging the screen out towards the viewer
+	vec2 position = TexCoords * 2.0 - 1.0;
+	position *= 1.0 + curvature * position.yx * position.yx;
+	vec2 coords = position * 0.5 + 0.5;
+	if (any(lessThan(coords, vec2(0.0))) || any(greaterThan(coords, vec2(1.0))))
+	{
+		color = blend(vec4(0.0, 0.0, 0.0, 1.0));
+		return;
+	}
+
+	// chromatic aberration, red and blue drift apart towards the edges
+	vec2 shift = position * aberration * texelSize;
+	vec3 crt = vec3(
+		texture(scene, coords + shift).r,
+		texture(scene, coords).g,
+		texture(scene, coords - shift).b
+	);
+
+	// a dark line between every other row of pixels
+	float line = sin(coords.y / texelSize.y * PI) * 0.5 + 0.5;
+	crt *= 1.0 - scanlines * (1.0 - line);
+
+	// aperture grille, every column of pixels only lets through one of red, green and blue
+	vec3 mask = vec3(1.0 - phosphorMask);
+	mask[int(gl_FragCoord.x) % 3] = 1.0;
+	crt *= mask;
+
+	// darker towards the corners
+	crt *= clamp(1.0 - vignette * dot(position, position) * 0.5, 0.0, 1.0);
+
+	color = blend(vec4(crt, 1.0));
+}
@@ -3,11 +3,8 @@ layout (location = 0) in vec4 vertex; // <vec2 position, vec2 texCoords>
 
 out vec2 TexCoords;
 
-uniform mat4 model;
-uniform mat4 projection;
-
 void main()
 {
+    gl_Position = vec4(vertex.xy, 0.0f, 1.0f);
     TexCoords = vertex.zw;
-    gl_Position = projection * model * vec4(vertex.xy, 0.0, 1.0);
-}
\ No newline at end of file
+}
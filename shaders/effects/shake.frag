@@ -0,0 +1,11 @@
+#version 330 core
+#include "../include/effects.glsl"
+
+uniform vec2 offsets[KERNEL_SIZE];
+uniform float kernel[KERNEL_SIZE];
+
+void main()
+{
+	// the camera moves the scene around, this only blurs it while it shakes
+	color = blend(vec4(convolve(TexCoords, offsets, kernel), 1.0f));
+}
@@ -0,0 +1,34 @@
+// Inputs and helpers shared by every post processing effect
+
+in vec2 TexCoords;
+out vec4 color;
+
+// output of the previous effect
+uniform sampler2D scene;
+uniform float time;
+// how strongly the effect is blended over the scene, from 0 to 1
+uniform float intensity;
+// size of one pixel in texture coordinates
+uniform vec2 texelSize;
+
+// number of samples of the convolution kernels, defined by the post processor
+#ifndef KERNEL_SIZE
+#define KERNEL_SIZE 9
+#endif
+
+// weighted sum of the scene sampled around a point, the offsets are in pixels
+vec3 convolve(vec2 coords, vec2 offsets[KERNEL_SIZE], float kernel[KERNEL_SIZE])
+{
+	vec3 sum = vec3(0.0);
+	for (int i = 0; i < KERNEL_SIZE; i++)
+	{
+		sum += texture(scene, coords + offsets[i] * texelSize).rgb * kernel[i];
+	}
+	return sum;
+}
+
+// blends the result of an effect over the scene by the intensity of the effect
+vec4 blend(vec4 effect)
+{
+	return mix(texture(scene, TexCoords), effect, intensity);
+}
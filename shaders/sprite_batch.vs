@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec2 texCoords;
+layout (location = 2) in vec4 color;
+
+out vec2 TexCoords;
+out vec4 SpriteColor;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main()
+{
+    TexCoords = texCoords;
+    SpriteColor = color;
+    gl_Position = projection * view * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,57 @@
+#version 330 core
+in vec2 TexCoords;
+out vec4 color;
+
+uniform sampler2D text;
+uniform vec3 textColor;
+// change of the distance field value over one pixel on screen
+uniform float distancePerPixel;
+// size on screen of one pixel of the distance field
+uniform float glyphScale;
+
+// widths and offsets are in pixels, effects with a transparent color are disabled
+uniform vec4 outlineColor;
+uniform float outlineWidth;
+uniform vec4 shadowColor;
+uniform vec2 shadowOffset;
+uniform vec4 glowColor;
+uniform float glowWidth;
+
+// distance field value on the outline of a glyph
+const float EDGE = 0.5;
+
+float coverage(float distance, float edge)
+{
+    float smoothing = fwidth(distance) * 0.5;
+    return smoothstep(edge - smoothing, edge + smoothing, distance);
+}
+
+// draws the top color over the bottom one
+vec4 over(vec4 top, vec4 bottom)
+{
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+    vec3 rgb = (top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / max(alpha, 0.0001);
+    return vec4(rgb, alpha);
+}
+
+void main()
+{
+    float distance = texture(text, TexCoords).r;
+    // the outline grows the glyph, the shadow and the glow start from its outer edge
+    float outerEdge = EDGE - outlineWidth * distancePerPixel;
+
+    vec4 result = vec4(0.0);
+    if (glowColor.a > 0.0 && glowWidth > 0.0) {
+        float glow = smoothstep(outerEdge - glowWidth * distancePerPixel, outerEdge, distance);
+        result = vec4(glowColor.rgb, glowColor.a * glow);
+    }
+    if (shadowColor.a > 0.0) {
+        vec2 offset = shadowOffset / (vec2(textureSize(text, 0)) * glyphScale);
+        float shadow = coverage(texture(text, TexCoords - offset).r, outerEdge);
+        result = over(vec4(shadowColor.rgb, shadowColor.a * shadow), result);
+    }
+    if (outlineColor.a > 0.0 && outlineWidth > 0.0) {
+        result = over(vec4(outlineColor.rgb, outlineColor.a * coverage(distance, outerEdge)), result);
+    }
+    color = over(vec4(textColor, coverage(distance, EDGE)), result);
+}
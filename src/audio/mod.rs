@@ -0,0 +1,166 @@
+#![allow(dead_code)]
+pub mod music_player;
+pub mod sound_manager;
+
+use kira::{
+    manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
+    sound::PlaybackRate,
+};
+use nalgebra_glm as glm;
+
+use music_player::{MusicCue, MusicPlayer};
+use sound_manager::SoundManager;
+
+/// How far sounds are allowed to move away from the center, 1.0 being hard left/right
+const PAN_WIDTH: f64 = 0.8;
+
+/// Major pentatonic scale in semitones, so consecutive combo hits climb in steps that
+/// always sound pleasant together. It stops at one octave, higher sounds start to chirp.
+const COMBO_SCALE: [i32; 6] = [0, 2, 4, 7, 9, 12];
+
+/// Maximum detune in semitones applied based on a brick's colour
+const COLOR_DETUNE: f32 = 1.5;
+
+pub struct Audio {
+    // None when there is no audio device, the game then runs without sound
+    pub manager: Option<AudioManager>,
+    pub sound_manager: SoundManager,
+    pub music_player: MusicPlayer,
+    width: u32,
+}
+
+impl Audio {
+    pub fn new(width: u32) -> Self {
+        Self {
+            manager: AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+                .map_err(|err| eprintln!("ERROR::AUDIO: Failed to open audio device: {:?}", err))
+                .ok(),
+            sound_manager: SoundManager::new(),
+            music_player: MusicPlayer::new(),
+            width,
+        }
+    }
+
+    /// Plays a preloaded sound panned according to the x position in the playfield.
+    /// `semitones` shifts the pitch up or down relative to the original sound.
+    pub fn play_sound_at(&mut self, name: &str, x: f32, semitones: f32) {
+        let Some(manager) = self.manager.as_mut() else {
+            return;
+        };
+        // a sound that failed to load was already reported
+        let Some(sound) = self.sound_manager.get_sound(name) else {
+            return;
+        };
+        let sound = sound.with_modified_settings(|settings| {
+            settings
+                .panning(pan_for_position(x, self.width))
+                .playback_rate(PlaybackRate::Factor(pitch_factor(semitones)))
+        });
+        if let Err(err) = manager.play(sound) {
+            eprintln!("ERROR::AUDIO: Failed to play sound {}: {}", name, err);
+        }
+    }
+
+    pub fn play_music(&mut self, cue: MusicCue) {
+        if let Some(manager) = self.manager.as_mut() {
+            self.music_player.play_cue(manager, cue);
+        }
+    }
+
+    pub fn update(&mut self) {
+        if let Some(manager) = self.manager.as_mut() {
+            self.music_player.update(manager);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.music_player.stop();
+        self.sound_manager.clear();
+    }
+}
+
+/// Maps an x position in the playfield to a kira panning value, where 0.0 is
+/// hard left, 0.5 is center and 1.0 is hard right
+pub fn pan_for_position(x: f32, width: u32) -> f64 {
+    let normalized = (x / width as f32).clamp(0.0, 1.0) as f64;
+    0.5 + (normalized - 0.5) * PAN_WIDTH
+}
+
+/// Converts a shift in semitones to a playback rate factor
+pub fn pitch_factor(semitones: f32) -> f64 {
+    2.0_f64.powf(semitones as f64 / 12.0)
+}
+
+/// Pitch offset for a combo counter, climbing the scale and holding at the top
+pub fn combo_semitones(combo: u32) -> f32 {
+    let step = (combo as usize).min(COMBO_SCALE.len() - 1);
+    COMBO_SCALE[step] as f32
+}
+
+/// Slight detune derived from the hue of a brick, so every brick colour has its own voice
+pub fn color_semitones(color: &glm::Vec3) -> f32 {
+    let max = color.max();
+    let min = color.min();
+    let delta = max - min;
+    if delta <= f32::EPSILON {
+        // greyscale bricks (i.e. solid ones) stay at the original pitch
+        return 0.0;
+    }
+
+    let hue = if max == color.x {
+        ((color.y - color.z) / delta).rem_euclid(6.0)
+    } else if max == color.y {
+        (color.z - color.x) / delta + 2.0
+    } else {
+        (color.x - color.y) / delta + 4.0
+    } / 6.0;
+
+    (hue * 2.0 - 1.0) * COLOR_DETUNE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {} but got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn pans_within_the_pan_width() {
+        assert_near(pan_for_position(0.0, 800), 0.5 - PAN_WIDTH / 2.0);
+        assert_near(pan_for_position(400.0, 800), 0.5);
+        assert_near(pan_for_position(800.0, 800), 0.5 + PAN_WIDTH / 2.0);
+        // positions outside the playfield pan like its edges
+        assert_near(pan_for_position(-100.0, 800), 0.5 - PAN_WIDTH / 2.0);
+        assert_near(pan_for_position(900.0, 800), 0.5 + PAN_WIDTH / 2.0);
+    }
+
+    #[test]
+    fn octave_doubles_the_pitch() {
+        assert_near(pitch_factor(0.0), 1.0);
+        assert_near(pitch_factor(12.0), 2.0);
+        assert_near(pitch_factor(-12.0), 0.5);
+    }
+
+    #[test]
+    fn combo_holds_at_the_top_of_the_scale() {
+        assert_eq!(combo_semitones(0), 0.0);
+        assert_eq!(combo_semitones(3), COMBO_SCALE[3] as f32);
+        let top = COMBO_SCALE[COMBO_SCALE.len() - 1] as f32;
+        assert_eq!(combo_semitones(COMBO_SCALE.len() as u32 - 1), top);
+        assert_eq!(combo_semitones(100), top);
+    }
+
+    #[test]
+    fn grey_bricks_keep_their_pitch() {
+        assert_eq!(color_semitones(&glm::vec3(0.8, 0.8, 0.8)), 0.0);
+        let red = color_semitones(&glm::vec3(1.0, 0.0, 0.0));
+        assert!((-COLOR_DETUNE..=COLOR_DETUNE).contains(&red));
+    }
+}
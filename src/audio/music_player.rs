@@ -0,0 +1,305 @@
+#![allow(dead_code)]
+use std::{collections::HashMap, fs, path::Path, path::PathBuf, time::Duration};
+
+use kira::{
+    manager::AudioManager,
+    sound::{
+        streaming::{StreamingSoundData, StreamingSoundHandle, StreamingSoundSettings},
+        FromFileError, PlaybackState,
+    },
+    tween::Tween,
+};
+use rand::{seq::SliceRandom, Rng};
+
+const MUSIC_VOLUME: f64 = 0.5;
+const DUCKED_VOLUME: f64 = 0.15;
+const CROSSFADE_DURATION: Duration = Duration::from_millis(1500);
+const DUCK_DURATION: Duration = Duration::from_millis(300);
+
+/// The places in the game that can have their own music assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MusicCue {
+    Menu,
+    Level(u32),
+    Win,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MusicSource {
+    /// A single track which loops for as long as the cue is active
+    Track(PathBuf),
+    /// Every track in a directory, played back in shuffled order
+    Playlist(PathBuf),
+}
+
+pub struct MusicPlayer {
+    cues: HashMap<MusicCue, MusicSource>,
+    current_source: Option<MusicSource>,
+    current: Option<StreamingSoundHandle<FromFileError>>,
+    playlist: Playlist,
+    ducked: bool,
+}
+
+impl MusicPlayer {
+    pub fn new() -> Self {
+        Self {
+            cues: HashMap::new(),
+            current_source: None,
+            current: None,
+            playlist: Playlist::default(),
+            ducked: false,
+        }
+    }
+
+    pub fn assign(&mut self, cue: MusicCue, source: MusicSource) {
+        self.cues.insert(cue, source);
+    }
+
+    /// Crossfades to the music assigned to `cue`. Nothing happens if the cue resolves
+    /// to the music that is already playing, so e.g. levels sharing a track don't restart it.
+    pub fn play_cue(&mut self, manager: &mut AudioManager, cue: MusicCue) {
+        if !self.select(cue) {
+            return;
+        }
+        match self.current_source.clone() {
+            Some(MusicSource::Track(file)) => {
+                self.current = start_track(manager, &file, true, self.volume());
+            }
+            Some(MusicSource::Playlist(dir)) => {
+                self.playlist = Playlist::new(load_playlist(&dir));
+                self.play_next_in_playlist(manager);
+            }
+            None => {}
+        }
+    }
+
+    /// Makes the music assigned to `cue` the current music, fading out what was playing.
+    /// Returns false when it already is the current music.
+    fn select(&mut self, cue: MusicCue) -> bool {
+        let source = self.cues.get(&cue).cloned();
+        if source == self.current_source {
+            return false;
+        }
+        self.fade_out_current();
+        self.current_source = source;
+        true
+    }
+
+    /// Advances the playlist once the current track has finished
+    pub fn update(&mut self, manager: &mut AudioManager) {
+        if let Some(MusicSource::Playlist(_)) = self.current_source {
+            let finished = match &self.current {
+                Some(handle) => handle.state() == PlaybackState::Stopped,
+                None => true,
+            };
+            if finished && !self.playlist.is_empty() {
+                self.play_next_in_playlist(manager);
+            }
+        }
+    }
+
+    /// Lowers the music volume while the game is paused, and restores it afterwards
+    pub fn set_ducked(&mut self, ducked: bool) {
+        if self.ducked == ducked {
+            return;
+        }
+        self.ducked = ducked;
+        let volume = self.volume();
+        if let Some(handle) = self.current.as_mut() {
+            let tween = Tween {
+                duration: DUCK_DURATION,
+                ..Default::default()
+            };
+            if let Err(err) = handle.set_volume(volume, tween) {
+                eprintln!("ERROR::MUSIC: Failed to change volume: {}", err);
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.fade_out_current();
+        self.current_source = None;
+    }
+
+    fn play_next_in_playlist(&mut self, manager: &mut AudioManager) {
+        if let Some(file) = self.playlist.next(&mut rand::thread_rng()) {
+            self.current = start_track(manager, &file, false, self.volume());
+        }
+    }
+
+    fn fade_out_current(&mut self) {
+        if let Some(mut handle) = self.current.take() {
+            let tween = Tween {
+                duration: CROSSFADE_DURATION,
+                ..Default::default()
+            };
+            if let Err(err) = handle.stop(tween) {
+                eprintln!("ERROR::MUSIC: Failed to stop track: {}", err);
+            }
+        }
+    }
+
+    fn volume(&self) -> f64 {
+        if self.ducked {
+            DUCKED_VOLUME
+        } else {
+            MUSIC_VOLUME
+        }
+    }
+}
+
+/// Tracks of a playlist in the order they are played. Every track plays once before the
+/// order is shuffled again, so no track repeats before all of them were heard.
+#[derive(Debug, Default)]
+struct Playlist {
+    tracks: Vec<PathBuf>,
+    index: usize,
+}
+
+impl Playlist {
+    fn new(tracks: Vec<PathBuf>) -> Self {
+        Self { tracks, index: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    fn next(&mut self, rng: &mut impl Rng) -> Option<PathBuf> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+        if self.index >= self.tracks.len() {
+            self.tracks.shuffle(rng);
+            self.index = 0;
+        }
+        self.index += 1;
+        Some(self.tracks[self.index - 1].clone())
+    }
+}
+
+fn start_track(
+    manager: &mut AudioManager,
+    file: &Path,
+    looping: bool,
+    volume: f64,
+) -> Option<StreamingSoundHandle<FromFileError>> {
+    let mut settings = StreamingSoundSettings::new()
+        .volume(volume)
+        .fade_in_tween(Tween {
+            duration: CROSSFADE_DURATION,
+            ..Default::default()
+        });
+    if looping {
+        settings = settings.loop_region(..);
+    }
+
+    let data = match StreamingSoundData::from_file(file, settings) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!(
+                "ERROR::MUSIC: Failed to load track {}: {}",
+                file.display(),
+                err
+            );
+            return None;
+        }
+    };
+
+    match manager.play(data) {
+        Ok(handle) => Some(handle),
+        Err(err) => {
+            eprintln!(
+                "ERROR::MUSIC: Failed to play track {}: {}",
+                file.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Collects every supported audio file in `dir` in shuffled order
+fn load_playlist(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!(
+                "ERROR::MUSIC: Failed to read playlist directory {}: {}",
+                dir.display(),
+                err
+            );
+            return vec![];
+        }
+    };
+
+    let mut tracks: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("mp3" | "wav" | "ogg" | "flac")
+            )
+        })
+        .collect();
+    tracks.shuffle(&mut rand::thread_rng());
+    tracks
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn cues_switch_only_to_different_music() {
+        let theme = MusicSource::Track("theme.wav".into());
+        let mut player = MusicPlayer::new();
+        player.assign(MusicCue::Menu, theme.clone());
+        player.assign(MusicCue::Level(0), theme.clone());
+        player.assign(MusicCue::Level(1), MusicSource::Playlist("levels".into()));
+
+        assert!(player.select(MusicCue::Menu));
+        assert_eq!(player.current_source, Some(theme.clone()));
+        // the first level shares the menu theme, which keeps playing
+        assert!(!player.select(MusicCue::Level(0)));
+        assert_eq!(player.current_source, Some(theme));
+
+        assert!(player.select(MusicCue::Level(1)));
+        assert_eq!(
+            player.current_source,
+            Some(MusicSource::Playlist("levels".into()))
+        );
+        // a cue without music stops the music
+        assert!(player.select(MusicCue::Win));
+        assert_eq!(player.current_source, None);
+        assert!(!player.select(MusicCue::Level(5)));
+    }
+
+    #[test]
+    fn playlist_plays_every_track_once_per_cycle() {
+        let tracks: Vec<PathBuf> = ["a.wav", "b.wav", "c.wav", "d.wav"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut playlist = Playlist::new(tracks.clone());
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for _ in 0..5 {
+            let cycle: HashSet<PathBuf> = (0..tracks.len())
+                .map(|_| playlist.next(&mut rng).unwrap())
+                .collect();
+            assert_eq!(cycle, tracks.iter().cloned().collect());
+        }
+    }
+
+    #[test]
+    fn empty_playlist_plays_nothing() {
+        let mut playlist = Playlist::default();
+        assert!(playlist.is_empty());
+        assert_eq!(playlist.next(&mut StdRng::seed_from_u64(3)), None);
+    }
+}
@@ -0,0 +1,49 @@
+#![allow(dead_code)]
+use std::{collections::HashMap, path::Path};
+
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+
+#[derive(Debug)]
+pub struct SoundManager {
+    sounds: HashMap<String, StaticSoundData>,
+}
+
+impl SoundManager {
+    pub fn new() -> Self {
+        Self {
+            sounds: HashMap::new(),
+        }
+    }
+
+    /// Loads a sound under `name`. A sound that fails to load is reported and left out, so
+    /// playing it later does nothing instead of stopping the game.
+    pub fn load_sound(
+        &mut self,
+        file: &Path,
+        settings: StaticSoundSettings,
+        name: &str,
+    ) -> Option<&StaticSoundData> {
+        match StaticSoundData::from_file(file, settings) {
+            Ok(sound) => {
+                self.sounds.insert(name.to_string(), sound);
+                self.sounds.get(name)
+            }
+            Err(err) => {
+                eprintln!(
+                    "ERROR::AUDIO: Failed to load sound {}: {}",
+                    file.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn get_sound(&self, name: &str) -> Option<&StaticSoundData> {
+        self.sounds.get(name)
+    }
+
+    pub fn clear(&mut self) {
+        self.sounds.clear();
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     game_object::GameObject,
-    graphics::{sprite_renderer::SpriteRenderer, texture::Texture2D},
+    graphics::{
+        sprite_batch::{layer, SpriteBatch},
+        texture::Texture2D,
+    },
 };
 use nalgebra_glm as glm;
 
@@ -58,8 +61,8 @@ impl Ball {
         self.object.position
     }
 
-    pub fn draw(&self, renderer: &mut SpriteRenderer, sprite: &Texture2D) {
-        self.object.draw(renderer, sprite);
+    pub fn draw(&self, renderer: &mut SpriteBatch, sprite: &Texture2D) {
+        self.object.draw(renderer, sprite, layer::BALL);
     }
 
     pub fn reset(&mut self, position: glm::Vec2, velocity: glm::Vec2) {
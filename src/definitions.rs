@@ -0,0 +1,143 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+
+use nalgebra_glm as glm;
+
+/// Properties of a single `[name]` section in a definition file
+pub type Properties = HashMap<String, String>;
+
+/// Splits the contents of a definition file into its sections. Definition files are made
+/// up of `[name]` headers, each followed by `key = value` lines. Empty lines and lines
+/// starting with `#` are ignored.
+pub fn parse_sections(contents: &str) -> Vec<(String, Properties)> {
+    let mut sections: Vec<(String, Properties)> = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), HashMap::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            match sections.last_mut() {
+                Some((_, properties)) => {
+                    properties.insert(key.trim().to_string(), value.trim().to_string());
+                }
+                None => eprintln!(
+                    "ERROR::DEFINITIONS: Property outside of a section: {}",
+                    line
+                ),
+            }
+        } else {
+            eprintln!("ERROR::DEFINITIONS: Could not parse line: {}", line);
+        }
+    }
+    sections
+}
+
+/// Parses a whitespace separated list of numbers, e.g. `1.0 0.5 0.0`
+pub fn parse_floats(value: &str) -> Option<Vec<f32>> {
+    value
+        .split_whitespace()
+        .map(|v| v.parse().ok())
+        .collect::<Option<Vec<f32>>>()
+}
+
+pub fn parse_vec2(value: &str) -> Option<glm::Vec2> {
+    match parse_floats(value)?.as_slice() {
+        [x, y] => Some(glm::vec2(*x, *y)),
+        _ => None,
+    }
+}
+
+pub fn parse_vec3(value: &str) -> Option<glm::Vec3> {
+    match parse_floats(value)?.as_slice() {
+        [x, y, z] => Some(glm::vec3(*x, *y, *z)),
+        _ => None,
+    }
+}
+
+pub fn parse_vec4(value: &str) -> Option<glm::Vec4> {
+    match parse_floats(value)?.as_slice() {
+        [x, y, z, w] => Some(glm::vec4(*x, *y, *z, *w)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_comments_and_empty_lines() {
+        let sections = parse_sections(
+            "# heading comment\n\
+             \n\
+             [ball]\n\
+             \x20 # indented comment\n\
+             radius = 12.5\n",
+        );
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].0, "ball");
+        assert_eq!(sections[0].1.len(), 1);
+        assert_eq!(sections[0].1["radius"], "12.5");
+    }
+
+    #[test]
+    fn trims_names_keys_and_values() {
+        let sections = parse_sections("  [ paddle ]  \n   width   =  100 200  \n");
+        assert_eq!(sections[0].0, "paddle");
+        assert_eq!(sections[0].1["width"], "100 200");
+    }
+
+    #[test]
+    fn drops_bad_lines_and_properties_outside_sections() {
+        let sections = parse_sections(
+            "orphan = 1\n\
+             [a]\n\
+             not a property\n\
+             [broken\n\
+             key = value\n",
+        );
+        // the unclosed header is a bad line, so `key` still belongs to `a`
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].0, "a");
+        assert_eq!(sections[0].1.len(), 1);
+        assert_eq!(sections[0].1["key"], "value");
+    }
+
+    #[test]
+    fn keeps_unknown_keys_and_the_last_duplicate() {
+        // which keys mean something is up to whoever reads the section
+        let sections = parse_sections("[a]\nwhatever = 1\nkey = first\nkey = second\n");
+        assert_eq!(sections[0].1["whatever"], "1");
+        assert_eq!(sections[0].1["key"], "second");
+    }
+
+    #[test]
+    fn splits_properties_at_the_first_equals_sign() {
+        let sections = parse_sections("[a]\ncommand = x --flag=1\nempty =\n");
+        assert_eq!(sections[0].1["command"], "x --flag=1");
+        assert_eq!(sections[0].1["empty"], "");
+    }
+
+    #[test]
+    fn keeps_sections_with_the_same_name_apart() {
+        let sections = parse_sections("[a]\nx = 1\n[a]\nx = 2\n[b]\n");
+        let names: Vec<&str> = sections.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a", "a", "b"]);
+        assert_eq!(sections[1].1["x"], "2");
+        assert!(sections[2].1.is_empty());
+    }
+
+    #[test]
+    fn parses_vectors_of_the_right_length_only() {
+        assert_eq!(parse_vec2("1 2"), Some(glm::vec2(1.0, 2.0)));
+        assert_eq!(parse_vec3(" 0.5  1.0 0 "), Some(glm::vec3(0.5, 1.0, 0.0)));
+        assert_eq!(parse_vec3("1 2"), None);
+        assert_eq!(parse_vec4("1 2 3 4 5"), None);
+        assert_eq!(parse_vec2("1 x"), None);
+        assert_eq!(parse_floats(""), Some(vec![]));
+    }
+}
@@ -1,37 +1,81 @@
 #![allow(dead_code)]
 
-use std::{ops::Neg, path::Path};
+use std::{collections::HashMap, fs, ops::Neg, path::Path};
 
-use glfw::ffi::glfwGetTime;
-use kira::{
-    manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
-    sound::static_sound::{StaticSoundData, StaticSoundSettings},
-};
+use kira::sound::static_sound::StaticSoundSettings;
 use nalgebra_glm as glm;
-use rand::random;
 
 use crate::{
+    audio::{
+        color_semitones, combo_semitones,
+        music_player::{MusicCue, MusicPlayer, MusicSource},
+        sound_manager::SoundManager,
+        Audio,
+    },
     ball::{Ball, BALL_RADIUS, INITIAL_BALL_VELOCITY},
     game_level::GameLevel,
     game_object::GameObject,
     graphics::{
-        post_processor::PostProcessor, shader_manager::ShaderManager, text_renderer::TextRenderer,
+        asset_error::AssetReport, sprite_batch::layer, texture_manager::TextureManager, Graphics,
+    },
+    graphics::{
+        bloom::Bloom,
+        post_effect::{builtin_effects, PostEffect},
+        post_processor::{PostProcessor, KERNEL_SIZE},
+        recorder::{Recorder, RecordingOutput},
+        screenshot::{self, read_framebuffer, save_screenshot},
+        shader_manager::ShaderManager,
+        text_renderer::{TextAlign, TextEffects, TextRenderer},
     },
-    graphics::{texture_manager::TextureManager, Graphics},
+    hud::Hud,
     particle_generator::ParticleGenerator,
-    powerup::{PowerUp, PowerUpType},
+    powerup::{PowerUp, PowerUpRegistry, PowerUpType, Stacking},
+    settings::Settings,
 };
 
 #[derive(Debug, PartialEq, Eq)]
 enum GameState {
     Active,
+    Paused,
     Menu,
     Win,
 }
 
 pub const PLAYER_SIZE: glm::Vec2 = glm::Vec2::new(100.0, 20.0);
 pub const PLAYER_VELOCITY: f32 = 500.0;
+const MAX_PLAYER_WIDTH: f32 = 250.0;
+const PAD_SIZE_INCREASE: f32 = 50.0;
+const MIN_PLAYER_WIDTH: f32 = 50.0;
+const PAD_SIZE_DECREASE: f32 = 40.0;
+const MAX_BALL_SPEED: f32 = 750.0;
+const MIN_BALL_SPEED: f32 = 200.0;
+const SPEED_INCREASE: f32 = 1.2;
+const SLOW_BALL_DECREASE: f32 = 0.7;
+const MAX_LIVES: u32 = 5;
+const LASER_SIZE: glm::Vec2 = glm::Vec2::new(6.0, 18.0);
+const LASER_VELOCITY: f32 = 600.0;
+const LASER_COOLDOWN: f32 = 0.5;
+const SHIELD_HEIGHT: f32 = 6.0;
+const MAGNET_STRENGTH: f32 = 3.0;
+const MAGNET_MAX_VELOCITY: f32 = 200.0;
+// size in pixels of the square textures the sprites are packed into
+const ATLAS_SIZE: u32 = 2048;
+// space around every sprite in the atlas, filled with its edge pixels against bleeding
+const ATLAS_PADDING: u32 = 2;
+const POWERUP_MESSAGE_DURATION: f32 = 1.5;
 const NUM_LIVES: u32 = 3;
+// how much the "YOU WON!!!" text grows and shrinks and how fast, in radians per second
+const WIN_PULSE_AMOUNT: f32 = 0.15;
+const WIN_PULSE_SPEED: f32 = 4.0;
+// seconds the chaos and confuse effects take to fade in and out
+const EFFECT_FADE_TIME: f32 = 0.25;
+// camera trauma added by hitting a solid brick and how far it kicks the view along the ball
+const SOLID_HIT_TRAUMA: f32 = 0.45;
+const SOLID_HIT_KICK: f32 = 6.0;
+// the camera zooms in on the final brick of a level for a moment, the win screen follows
+// once it is done
+const FINAL_BRICK_ZOOM: f32 = 1.5;
+const FINAL_BRICK_FOCUS_TIME: f32 = 0.6;
 
 pub struct Game {
     state: GameState,
@@ -44,18 +88,37 @@ pub struct Game {
     ball: Ball,
     particle_generator: ParticleGenerator,
     effects: PostProcessor,
-    shake_time: f32,
     powerups: Vec<PowerUp>,
-    audio_manager: AudioManager,
+    powerup_registry: PowerUpRegistry,
+    // label of the last collected powerup and how long it is still shown
+    powerup_message: Option<(String, f32)>,
+    lasers: Vec<GameObject>,
+    laser_cooldown: f32,
+    shield: GameObject,
+    hud: Hud,
+    audio: Audio,
     text_renderer: TextRenderer,
     lives: u32,
+    // seconds left until the win screen while the camera shows the final brick of the level
+    win_delay: Option<f32>,
+    // number of bricks destroyed since the ball last touched the paddle
+    combo: u32,
+    // whether the renderer statistics are shown
+    show_stats: bool,
+    // a screenshot is taken at the end of the next frame
+    screenshot_requested: bool,
+    recorder: Option<Recorder>,
+    // seconds the game has been running, advanced by the time step so effects animate the
+    // same in recordings
+    time: f32,
+    settings: Settings,
 }
 
 impl Game {
     pub fn new(mut graphics: Graphics) -> Self {
         // load textures
-        load_textures(&mut graphics.texture_manager);
-        load_shaders(&mut graphics.shader_manager);
+        load_textures(&mut graphics.texture_manager, &mut graphics.asset_report);
+        load_shaders(&mut graphics.shader_manager, &mut graphics.asset_report);
 
         let mut levels = vec![];
         load_levels(
@@ -89,22 +152,59 @@ impl Game {
         let mut particle_generator = ParticleGenerator::new(
             graphics.shader_manager.get_shader("particle").clone(),
             graphics.texture_manager.get_texture("particle").clone(),
-            500,
+            10000,
         );
         particle_generator.init();
+        particle_generator.load_emitters(Path::new("resources/emitters.def"));
 
-        let effects = PostProcessor::new(
-            graphics.shader_manager.get_shader("postprocessing").clone(),
+        let mut effects = PostProcessor::new(graphics.width as i32, graphics.height as i32);
+        for effect in builtin_effects(&graphics.shader_manager) {
+            effects.add_effect(effect);
+        }
+        // the CRT filter goes last, it is the screen everything else is shown on
+        effects.add_effect(PostEffect::new(
+            "crt",
+            graphics.shader_manager.get_shader("crt").clone(),
+        ));
+        effects.bloom = Some(Bloom::new(
+            &graphics.shader_manager,
             graphics.width as i32,
             graphics.height as i32,
-        );
+        ));
+        let settings = Settings::load(Path::new("resources/settings.def"));
+        settings.apply(&mut effects);
 
         let mut text_renderer = TextRenderer::new(
-            graphics.width,
-            graphics.height,
+            &graphics.camera.projection(),
             graphics.shader_manager.get_shader("text").clone(),
+            graphics.shader_manager.get_shader("text_sdf").clone(),
         );
-        text_renderer.load("resources/fonts/OCRAEXT.TTF".to_string(), 24);
+        graphics.asset_report.record(text_renderer.load(
+            Path::new("resources/fonts/OCRAEXT.TTF"),
+            "ocraext",
+            24,
+        ));
+
+        let shield = GameObject {
+            position: glm::vec2(0.0, graphics.height as f32 - SHIELD_HEIGHT),
+            size: glm::vec2(graphics.width as f32, SHIELD_HEIGHT),
+            color: glm::vec3(0.3, 1.0, 1.0),
+            destroyed: true,
+            sprite_id: String::from("block"),
+            ..Default::default()
+        };
+
+        let hud = Hud::new(graphics.width);
+
+        let mut powerup_registry = PowerUpRegistry::new();
+        powerup_registry.load(Path::new("resources/powerups.def"));
+
+        let mut audio = Audio::new(graphics.width);
+        load_sounds(&mut audio.sound_manager);
+        load_music(&mut audio.music_player);
+
+        // everything is loaded, so list all assets that are missing or broken at once
+        graphics.asset_report.print();
 
         Self {
             state: GameState::Menu,
@@ -117,62 +217,66 @@ impl Game {
             ball,
             particle_generator,
             effects,
-            shake_time: 0.0,
             powerups: vec![],
-            audio_manager: AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
-                .unwrap(),
+            powerup_registry,
+            powerup_message: None,
+            lasers: vec![],
+            laser_cooldown: 0.0,
+            shield,
+            hud,
+            audio,
             text_renderer,
             lives: NUM_LIVES,
+            win_delay: None,
+            combo: 0,
+            show_stats: false,
+            screenshot_requested: false,
+            recorder: None,
+            time: 0.0,
+            settings,
         }
     }
 
     pub fn init(&mut self) {
         // configure shaders
-        let projection = glm::ortho(
-            0.0,
-            self.graphics.width as f32,
-            self.graphics.height as f32,
-            0.0,
-            -1.0,
-            1.0,
-        );
-        self.graphics
-            .shader_manager
-            .get_shader("sprite")
-            .use_program()
-            .set_int("image\0", 0);
-
-        self.graphics
-            .shader_manager
-            .get_shader("sprite")
-            .set_mat4("projection\0", &projection);
-
+        let projection = self.graphics.camera.projection();
         self.graphics
             .shader_manager
             .get_shader("particle")
             .use_program()
-            .set_int("sprite\0", 0);
+            .set_int("sprite", 0);
 
         self.graphics
             .shader_manager
             .get_shader("particle")
-            .set_mat4("projection\0", &projection);
-
-        let sound_data = StaticSoundData::from_file(
-            "resources/audio/breakout.mp3",
-            StaticSoundSettings::new().loop_region(..).volume(0.5),
-        )
-        .unwrap();
-        self.audio_manager.play(sound_data).unwrap();
+            .set_mat4("projection", &projection);
+        self.graphics.set_view(&self.graphics.camera.view());
+
+        self.audio.play_music(MusicCue::Menu);
     }
 
     pub fn process_input(&mut self, dt: f64) {
+        if self.keys[glfw::Key::F3 as usize] && !self.keys_processed[glfw::Key::F3 as usize] {
+            self.keys_processed[glfw::Key::F3 as usize] = true;
+            self.show_stats = !self.show_stats;
+        }
+        if self.keys[glfw::Key::F12 as usize] && !self.keys_processed[glfw::Key::F12 as usize] {
+            self.keys_processed[glfw::Key::F12 as usize] = true;
+            self.screenshot_requested = true;
+        }
+        if self.keys[glfw::Key::F10 as usize] && !self.keys_processed[glfw::Key::F10 as usize] {
+            self.keys_processed[glfw::Key::F10 as usize] = true;
+            self.toggle_recording();
+        }
+
         match self.state {
             GameState::Menu => {
                 if self.keys[glfw::Key::Enter as usize]
                     && !self.keys_processed[glfw::Key::Enter as usize]
                 {
                     self.state = GameState::Active;
+                    self.graphics.camera.reset();
+                    self.audio.play_music(MusicCue::Level(self.level));
                     self.keys_processed[glfw::Key::Enter as usize] = true;
                 }
                 if self.keys[glfw::Key::W as usize] && !self.keys_processed[glfw::Key::W as usize] {
@@ -191,8 +295,17 @@ impl Game {
             GameState::Win => {
                 if self.keys[glfw::Key::Enter as usize] {
                     self.keys_processed[glfw::Key::Enter as usize] = true;
-                    self.effects.chaos = false;
+                    self.effects.set_enabled("chaos", false);
                     self.state = GameState::Menu;
+                    self.graphics.camera.reset();
+                    self.audio.play_music(MusicCue::Menu);
+                }
+            }
+            GameState::Paused => {
+                if self.keys[glfw::Key::P as usize] && !self.keys_processed[glfw::Key::P as usize] {
+                    self.keys_processed[glfw::Key::P as usize] = true;
+                    self.state = GameState::Active;
+                    self.audio.music_player.set_ducked(false);
                 }
             }
             GameState::Active => {
@@ -218,11 +331,24 @@ impl Game {
                 if self.keys[glfw::Key::Space as usize] {
                     self.ball.stuck = false;
                 }
+
+                if self.keys[glfw::Key::P as usize] && !self.keys_processed[glfw::Key::P as usize] {
+                    self.keys_processed[glfw::Key::P as usize] = true;
+                    self.state = GameState::Paused;
+                    self.audio.music_player.set_ducked(true);
+                }
             }
         }
     }
 
     pub fn update(&mut self, dt: f64) {
+        self.time += dt as f32;
+        self.audio.update();
+        self.graphics.shader_manager.update(dt as f32);
+        if self.state == GameState::Paused {
+            return;
+        }
+
         self.ball.move_ball(dt as f32, self.graphics.width);
         self.do_collisions();
         self.particle_generator.update(
@@ -232,72 +358,114 @@ impl Game {
             glm::vec2(self.ball.radius / 2.0, self.ball.radius / 2.0),
         );
         self.update_powerups(dt as f32);
+        self.hud.update(dt as f32);
 
-        // reduce shake time
-        if self.shake_time > 0.0 {
-            self.shake_time -= dt as f32;
-            if self.shake_time <= 0.0 {
-                self.effects.shake = false;
-            }
+        self.effects.update(dt as f32);
+
+        // the scene blurs as much as the camera shakes
+        self.graphics.camera.update(dt as f32);
+        if let Some(shake) = self.effects.effect_mut("shake") {
+            shake.set_intensity(self.graphics.camera.shake());
         }
 
-        // check loss condition
-        if self.ball.position().y >= self.graphics.height as f32 {
+        // check loss condition, the level is already won while the final brick is shown
+        if self.win_delay.is_none() && self.ball.position().y >= self.graphics.height as f32 {
+            self.particle_generator.emit(
+                "life_lost",
+                glm::vec2(
+                    self.ball.position().x + self.ball.radius,
+                    self.graphics.height as f32,
+                ),
+                self.ball.object.size,
+                glm::vec3(1.0, 1.0, 1.0),
+            );
             self.lives -= 1;
+            self.combo = 0;
             if self.lives == 0 {
                 self.reset_level();
                 self.state = GameState::Menu;
+                self.audio.play_music(MusicCue::Menu);
             }
             self.reset_player();
         }
 
-        // check win condition
-        if self.state == GameState::Active
-            && self.levels.get(self.level as usize).unwrap().is_completed()
-        {
-            self.reset_level();
-            self.reset_player();
-            self.effects.chaos = true;
-            self.state = GameState::Win;
+        // check win condition, the win screen waits until the camera has shown the final brick
+        let won = self.state == GameState::Active
+            && self.levels.get(self.level as usize).unwrap().is_completed();
+        if !won {
+            self.win_delay = None;
+        } else {
+            let win_delay = self.win_delay.get_or_insert(FINAL_BRICK_FOCUS_TIME);
+            *win_delay -= dt as f32;
+            if *win_delay <= 0.0 {
+                self.win_delay = None;
+                self.reset_level();
+                self.reset_player();
+                self.effects.set_enabled("chaos", true);
+                self.state = GameState::Win;
+                self.audio.play_music(MusicCue::Win);
+            }
         }
     }
 
     pub fn render(&mut self) {
         if self.state == GameState::Active
+            || self.state == GameState::Paused
             || self.state == GameState::Menu
             || self.state == GameState::Win
         {
+            self.graphics.sprite_batch.begin_frame();
             self.effects.begin_render();
             self.graphics.render();
             if let Some(level) = self.levels.get_mut(self.level as usize) {
                 level.draw(
-                    &mut self.graphics.sprite_renderer,
+                    &mut self.graphics.sprite_batch,
                     &self.graphics.texture_manager,
                 );
             }
+            if !self.shield.destroyed {
+                self.shield.draw(
+                    &mut self.graphics.sprite_batch,
+                    self.graphics.texture_manager.get_texture("block"),
+                    layer::OBJECTS,
+                );
+            }
+            for laser in &self.lasers {
+                laser.draw(
+                    &mut self.graphics.sprite_batch,
+                    self.graphics.texture_manager.get_texture("block"),
+                    layer::OBJECTS,
+                );
+            }
             self.player.draw(
-                &mut self.graphics.sprite_renderer,
+                &mut self.graphics.sprite_batch,
                 self.graphics.texture_manager.get_texture("paddle"),
+                layer::OBJECTS,
             );
             for powerup in &self.powerups {
                 if !powerup.object.destroyed {
                     powerup.object.draw(
-                        &mut self.graphics.sprite_renderer,
+                        &mut self.graphics.sprite_batch,
                         self.graphics
                             .texture_manager
                             .get_texture(&powerup.object.sprite_id),
+                        layer::OBJECTS,
                     );
                 }
             }
+            // particles are drawn between the objects and the ball, so the batch is flushed
+            // around them to keep that order
+            self.graphics.sprite_batch.flush();
             self.particle_generator.draw();
             self.ball.draw(
-                &mut self.graphics.sprite_renderer,
+                &mut self.graphics.sprite_batch,
                 self.graphics.texture_manager.get_texture("ball"),
             );
+            self.graphics.sprite_batch.flush();
             self.effects.end_render();
-            unsafe {
-                self.effects.render(glfwGetTime() as f32);
-            }
+            self.effects.render(self.time);
+            // the HUD stays in place while the camera moves the world
+            self.graphics.set_view(&glm::Mat4::identity());
             self.text_renderer.render_text(
                 &format!("Lives: {}", self.lives),
                 5.0,
@@ -305,43 +473,234 @@ impl Game {
                 1.0,
                 glm::vec3(1.0, 1.0, 1.0),
             );
+            self.hud.draw(
+                &self.powerups,
+                &mut self.graphics.sprite_batch,
+                &self.graphics.texture_manager,
+                &mut self.text_renderer,
+            );
+            if let Some((label, _)) = &self.powerup_message {
+                // the message pops up in the playfield, so it moves with the world
+                self.text_renderer.set_view(&self.graphics.camera.view());
+                self.text_renderer.render_text_aligned(
+                    label,
+                    0.0,
+                    self.graphics.height as f32 / 2.0 + 60.0,
+                    self.graphics.width as f32,
+                    1.0,
+                    glm::vec3(1.0, 1.0, 1.0),
+                    TextAlign::Center,
+                );
+                self.text_renderer.set_view(&glm::Mat4::identity());
+            }
         }
+        let line_height = self.text_renderer.line_height(1.0);
         if self.state == GameState::Menu {
-            self.text_renderer.render_text(
+            self.text_renderer.render_text_aligned(
                 "Press ENTER to start",
-                250.0,
+                0.0,
                 self.graphics.height as f32 / 2.0,
+                self.graphics.width as f32,
                 1.0,
                 glm::vec3(1.0, 1.0, 1.0),
+                TextAlign::Center,
             );
-            self.text_renderer.render_text(
+            self.text_renderer.render_text_aligned(
                 "Press W or S to select level",
-                245.0,
-                self.graphics.height as f32 / 2.0 + 20.0,
+                0.0,
+                self.graphics.height as f32 / 2.0 + line_height,
+                self.graphics.width as f32,
                 0.75,
                 glm::vec3(1.0, 1.0, 1.0),
+                TextAlign::Center,
             );
         }
-        if self.state == GameState::Win {
-            self.text_renderer.render_text(
-                "YOU WON!!!",
-                320.0,
-                self.graphics.height as f32 / 2.0 - 20.0,
+        if self.state == GameState::Paused {
+            self.text_renderer.render_text_aligned(
+                "PAUSED",
+                0.0,
+                self.graphics.height as f32 / 2.0,
+                self.graphics.width as f32,
                 1.0,
                 glm::vec3(1.0, 1.0, 1.0),
+                TextAlign::Center,
             );
-            self.text_renderer.render_text(
+        }
+        if self.state == GameState::Win {
+            // the bottom of the title stays put, so it grows upwards away from the line below
+            let pulse = 1.5 + WIN_PULSE_AMOUNT * (self.time * WIN_PULSE_SPEED).sin();
+            let title_height = line_height * pulse;
+            self.text_renderer.render_text_sdf(
+                "YOU WON!!!",
+                0.0,
+                self.graphics.height as f32 / 2.0 - title_height,
+                self.graphics.width as f32,
+                pulse,
+                glm::vec3(1.0, 0.9, 0.3),
+                TextAlign::Center,
+                &TextEffects {
+                    outline: Some((glm::vec4(0.4, 0.1, 0.0, 1.0), 2.0)),
+                    shadow: Some((glm::vec4(0.0, 0.0, 0.0, 0.6), glm::vec2(3.0, 3.0))),
+                    glow: Some((glm::vec4(1.0, 0.6, 0.1, 0.8), 6.0)),
+                },
+            );
+            self.text_renderer.render_text_aligned(
                 "Press ENTER to retry or ESC to quit",
-                130.0,
+                0.0,
                 self.graphics.height as f32 / 2.0,
+                self.graphics.width as f32,
                 1.0,
                 glm::vec3(1.0, 1.0, 1.0),
+                TextAlign::Center,
             );
         }
+        if self.show_stats {
+            let stats = self.graphics.sprite_batch.stats();
+            self.text_renderer.render_text(
+                &format!("Draw calls: {}  Quads: {}", stats.draw_calls, stats.quads),
+                5.0,
+                self.graphics.height as f32 - 20.0,
+                0.5,
+                glm::vec3(1.0, 1.0, 0.0),
+            );
+        }
+
+        // shaders that failed to reload keep running the last program that worked, show why
+        let errors: Vec<String> = self
+            .graphics
+            .shader_manager
+            .reload_errors()
+            .map(str::to_string)
+            .collect();
+        let mut y = 5.0;
+        for error in errors {
+            for line in self
+                .text_renderer
+                .wrap(&error, self.graphics.width as f32 - 10.0, 0.5)
+            {
+                self.text_renderer
+                    .render_text(&line, 5.0, y, 0.5, glm::vec3(1.0, 0.2, 0.2));
+                y += self.text_renderer.line_height(0.5);
+            }
+        }
+
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            self.take_screenshot();
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.capture(self.effects.output());
+        }
+    }
+
+    /// While recording every frame advances the game by the same time, however long it took
+    /// to render, so recordings play back smoothly
+    pub fn fixed_time_step(&self) -> Option<f64> {
+        self.recorder
+            .as_ref()
+            .map(|_| 1.0 / self.settings.recording.fps.max(1) as f64)
+    }
+
+    /// Starts recording every frame, or stops the recording that is running
+    pub fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let frames = recorder.frames();
+            recorder.stop();
+            println!("Stopped recording after {} frames", frames);
+            return;
+        }
+
+        let settings = &self.settings.recording;
+        let timestamp = screenshot::timestamp();
+        let output = match settings.output.as_str() {
+            "frames" => RecordingOutput::Frames(settings.directory.join(&timestamp)),
+            "encoder" => RecordingOutput::Encoder(
+                settings
+                    .encoder
+                    .split_whitespace()
+                    .map(|argument| {
+                        argument
+                            .replace("{width}", &self.graphics.width.to_string())
+                            .replace("{height}", &self.graphics.height.to_string())
+                            .replace("{fps}", &settings.fps.to_string())
+                            .replace("{directory}", &settings.directory.to_string_lossy())
+                            .replace("{timestamp}", &timestamp)
+                    })
+                    .collect(),
+            ),
+            output => {
+                eprintln!("ERROR::RECORDER: Unknown recording output {}", output);
+                return;
+            }
+        };
+        if let Err(error) = fs::create_dir_all(&settings.directory) {
+            eprintln!(
+                "ERROR::RECORDER: Failed to create recording directory: {}",
+                error
+            );
+            return;
+        }
+        match Recorder::start(self.graphics.width, self.graphics.height, output) {
+            Ok(recorder) => {
+                println!("Started recording at {} frames per second", settings.fps);
+                self.recorder = Some(recorder);
+            }
+            Err(error) => eprintln!("ERROR::RECORDER: Failed to start recording: {}", error),
+        }
+    }
+
+    /// Saves the frame that was just rendered as a PNG, or the scene before post processing
+    /// depending on the settings
+    pub fn take_screenshot(&self) {
+        let image = if self.settings.screenshot.post_processing {
+            read_framebuffer(
+                self.effects.output(),
+                self.graphics.width,
+                self.graphics.height,
+            )
+        } else {
+            self.effects.capture_scene()
+        };
+        match save_screenshot(&image, &self.settings.screenshot.directory) {
+            Ok(file) => println!("Saved screenshot to {}", file.display()),
+            Err(error) => eprintln!("ERROR::SCREENSHOT: Failed to save screenshot: {}", error),
+        }
+    }
+
+    /// Starts playing a level from the beginning, skipping the menu
+    pub fn start_level(&mut self, level: u32) {
+        if level as usize >= self.levels.len() {
+            eprintln!("ERROR::GAME: No level {}", level);
+            return;
+        }
+        self.level = level;
+        self.reset_level();
+        self.reset_player();
+        self.state = GameState::Active;
+        self.audio.play_music(MusicCue::Level(self.level));
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Replaces the settings read from the settings file
+    pub fn apply_settings(&mut self, settings: Settings) {
+        settings.apply(&mut self.effects);
+        self.settings = settings;
+    }
+
+    pub fn effects_mut(&mut self) -> &mut PostProcessor {
+        &mut self.effects
     }
 
     pub fn clear(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.stop();
+        }
         self.graphics.clear();
+        self.text_renderer.font_manager.clear();
+        self.audio.clear();
     }
 
     fn reset_level(&mut self) {
@@ -365,7 +724,7 @@ impl Game {
                 &self.graphics.texture_manager,
             ),
             3 => self.levels.get_mut(3).unwrap().load(
-                Path::new("resources/levels/three.lvl"),
+                Path::new("resources/levels/four.lvl"),
                 self.graphics.width,
                 self.graphics.height / 2,
                 &self.graphics.texture_manager,
@@ -386,108 +745,54 @@ impl Game {
                 + glm::vec2(PLAYER_SIZE.x / 2.0 - BALL_RADIUS, -(BALL_RADIUS * 2.0)),
             INITIAL_BALL_VELOCITY,
         );
-        // also disable all active powerups
-        self.effects.chaos = false;
-        self.effects.confuse = false;
+        // also disable all active powerups, the player and ball have already been reset
+        // so there is nothing left to undo
+        self.powerups.retain(|powerup| !powerup.activated);
+        self.lasers.clear();
+        self.shield.destroyed = true;
+        self.effects.set_enabled("chaos", false);
+        self.effects.set_enabled("confuse", false);
         self.ball.passthrough = false;
         self.ball.sticky = false;
         self.player.color = glm::vec3(1.0, 1.0, 1.0);
         self.ball.object.color = glm::vec3(1.0, 1.0, 1.0);
+        // the camera does not carry a shake or a focus over into the next ball
+        self.graphics.camera.reset();
     }
 
-    fn spawn_powerups(powerups: &mut Vec<PowerUp>, block: &GameObject) {
-        // 1 in 75 chance
-        if should_spawn(75) {
-            powerups.push(PowerUp::new(
-                PowerUpType::Speed,
-                glm::vec3(0.5, 0.5, 1.0),
-                0.0,
-                block.position,
-                "powerup_speed".to_string(),
-            ));
-        }
-        if should_spawn(75) {
-            powerups.push(PowerUp::new(
-                PowerUpType::Sticky,
-                glm::vec3(1.0, 0.5, 1.0),
-                20.0,
-                block.position,
-                "powerup_sticky".to_string(),
-            ));
-        }
-        if should_spawn(75) {
-            powerups.push(PowerUp::new(
-                PowerUpType::PassThrough,
-                glm::vec3(0.5, 1.0, 0.5),
-                10.0,
-                block.position,
-                "powerup_passthrough".to_string(),
-            ));
-        }
-        if should_spawn(75) {
-            powerups.push(PowerUp::new(
-                PowerUpType::PadSizeIncrease,
-                glm::vec3(1.0, 0.6, 0.0),
-                0.0,
-                block.position,
-                "powerup_increase".to_string(),
-            ));
-        }
-        if should_spawn(15) {
-            powerups.push(PowerUp::new(
-                PowerUpType::Confuse,
-                glm::vec3(1.0, 0.3, 0.3),
-                15.0,
-                block.position,
-                "powerup_confuse".to_string(),
-            ));
-        }
-        if should_spawn(15) {
-            powerups.push(PowerUp::new(
-                PowerUpType::Chaos,
-                glm::vec3(0.9, 0.25, 0.25),
-                15.0,
-                block.position,
-                "powerup_chaos".to_string(),
-            ));
+    fn spawn_powerups(
+        powerups: &mut Vec<PowerUp>,
+        registry: &PowerUpRegistry,
+        weights: &HashMap<PowerUpType, u32>,
+        block: &GameObject,
+    ) {
+        if let Some(definition) = registry.pick(weights, &mut rand::thread_rng()) {
+            powerups.push(PowerUp::from_definition(definition, block.position));
         }
     }
 
     fn update_powerups(&mut self, dt: f32) {
+        let magnet = Self::is_other_powerup_active(&self.powerups, &PowerUpType::Magnet);
+        let player_center = self.player.position.x + self.player.size.x / 2.0;
         for i in 0..self.powerups.len() {
+            // pull falling powerups towards the paddle while the magnet is active
+            if magnet && !self.powerups[i].object.destroyed {
+                let powerup_center =
+                    self.powerups[i].object.position.x + self.powerups[i].object.size.x / 2.0;
+                self.powerups[i].object.velocity.x = ((player_center - powerup_center)
+                    * MAGNET_STRENGTH)
+                    .clamp(-MAGNET_MAX_VELOCITY, MAGNET_MAX_VELOCITY);
+            } else {
+                self.powerups[i].object.velocity.x = 0.0;
+            }
+
             let delta_pos = self.powerups[i].object.velocity * dt;
             self.powerups[i].object.position += delta_pos;
             if self.powerups[i].activated {
                 self.powerups[i].duration -= dt;
                 if self.powerups[i].duration <= 0.0 {
                     self.powerups[i].activated = false;
-                    let powerup_type = &self.powerups[i].r#type;
-                    match powerup_type {
-                        PowerUpType::Sticky => {
-                            if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
-                                self.ball.sticky = false;
-                                self.player.color = glm::vec3(1.0, 1.0, 1.0);
-                            }
-                        }
-                        PowerUpType::PassThrough => {
-                            if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
-                                self.ball.passthrough = false;
-                                self.ball.object.color = glm::vec3(1.0, 1.0, 1.0);
-                            }
-                        }
-                        PowerUpType::Confuse => {
-                            if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
-                                self.effects.confuse = false;
-                            }
-                        }
-                        PowerUpType::Chaos => {
-                            if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
-                                self.effects.chaos = false;
-                            }
-                        }
-                        PowerUpType::Speed => {}
-                        PowerUpType::PadSizeIncrease => {}
-                    }
+                    self.deactivate_powerup(i);
                 }
             }
         }
@@ -498,6 +803,92 @@ impl Game {
                 !delete
             })
         }
+
+        self.update_lasers(dt);
+
+        if let Some((_, time_left)) = self.powerup_message.as_mut() {
+            *time_left -= dt;
+            if *time_left <= 0.0 {
+                self.powerup_message = None;
+            }
+        }
+    }
+
+    /// Fires bolts from both ends of the paddle while the laser is active and moves the
+    /// ones already fired
+    fn update_lasers(&mut self, dt: f32) {
+        if Self::is_other_powerup_active(&self.powerups, &PowerUpType::Laser) && !self.ball.stuck {
+            self.laser_cooldown -= dt;
+            if self.laser_cooldown <= 0.0 {
+                self.laser_cooldown = LASER_COOLDOWN;
+                for x in [
+                    self.player.position.x,
+                    self.player.position.x + self.player.size.x - LASER_SIZE.x,
+                ] {
+                    self.lasers.push(GameObject::new(
+                        glm::vec2(x, self.player.position.y - LASER_SIZE.y),
+                        LASER_SIZE,
+                        glm::vec3(1.0, 0.9, 0.3),
+                        glm::vec2(0.0, -LASER_VELOCITY),
+                        String::from("block"),
+                    ));
+                }
+            }
+        }
+
+        for laser in &mut self.lasers {
+            laser.position += laser.velocity * dt;
+            if laser.position.y + laser.size.y <= 0.0 {
+                laser.destroyed = true;
+            }
+        }
+        self.lasers.retain(|laser| !laser.destroyed);
+    }
+
+    /// Undoes the effect of a powerup that ran out
+    fn deactivate_powerup(&mut self, index: usize) {
+        let powerup_type = &self.powerups[index].r#type;
+        match powerup_type {
+            PowerUpType::Sticky => {
+                if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
+                    self.ball.sticky = false;
+                    self.player.color = glm::vec3(1.0, 1.0, 1.0);
+                }
+            }
+            PowerUpType::PassThrough => {
+                if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
+                    self.ball.passthrough = false;
+                    self.ball.object.color = glm::vec3(1.0, 1.0, 1.0);
+                }
+            }
+            PowerUpType::Confuse => {
+                if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
+                    self.effects.fade("confuse", false, EFFECT_FADE_TIME);
+                }
+            }
+            PowerUpType::Chaos => {
+                if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
+                    self.effects.fade("chaos", false, EFFECT_FADE_TIME);
+                }
+            }
+            PowerUpType::Speed | PowerUpType::SlowBall => {
+                self.ball.object.velocity /= self.powerups[index].applied;
+            }
+            PowerUpType::PadSizeIncrease | PowerUpType::Shrink => {
+                Self::resize_player(
+                    &mut self.player,
+                    -self.powerups[index].applied,
+                    self.graphics.width,
+                );
+            }
+            PowerUpType::Shield => {
+                if !Self::is_other_powerup_active(&self.powerups, powerup_type) {
+                    self.shield.destroyed = true;
+                }
+            }
+            // the laser and magnet only act while active, and an extra life is kept
+            PowerUpType::Laser | PowerUpType::ExtraLife | PowerUpType::Magnet => {}
+        }
     }
 
     fn is_other_powerup_active(powerups: &Vec<PowerUp>, r#type: &PowerUpType) -> bool {
@@ -509,14 +900,49 @@ impl Game {
         false
     }
 
-    fn activate_powerup(
-        ball: &mut Ball,
-        player: &mut GameObject,
-        powerup: &PowerUp,
-        effects: &mut PostProcessor,
-    ) {
+    /// Picks up a power-up, either activating it or, depending on the stacking rule of its
+    /// type, refreshing or extending a power-up of the same type that is already running
+    fn collect_powerup(&mut self, index: usize) {
+        let r#type = self.powerups[index].r#type;
+        let definition = self.powerup_registry.get_definition(r#type);
+        let stacking = definition.map_or(Stacking::Stack, |definition| definition.stacking);
+        if let Some(definition) = definition {
+            self.powerup_message = Some((definition.label.clone(), POWERUP_MESSAGE_DURATION));
+        }
+        let running = self
+            .powerups
+            .iter()
+            .position(|powerup| powerup.activated && powerup.r#type == r#type);
+
+        match (stacking, running) {
+            (Stacking::Refresh, Some(running)) => {
+                self.powerups[running].duration = self.powerups[running]
+                    .duration
+                    .max(self.powerups[index].duration);
+            }
+            (Stacking::Extend, Some(running)) => {
+                self.powerups[running].duration += self.powerups[index].duration;
+                self.powerups[running].total_duration += self.powerups[index].duration;
+            }
+            _ => {
+                self.activate_powerup(index);
+                self.powerups[index].activated = true;
+            }
+        }
+    }
+
+    fn activate_powerup(&mut self, index: usize) {
+        let ball = &mut self.ball;
+        let player = &mut self.player;
+        let effects = &mut self.effects;
+        let powerup = &mut self.powerups[index];
         match powerup.r#type {
-            PowerUpType::Speed => ball.object.velocity *= 1.2,
+            PowerUpType::Speed => {
+                powerup.applied = Self::scale_ball_speed(ball, SPEED_INCREASE);
+            }
+            PowerUpType::SlowBall => {
+                powerup.applied = Self::scale_ball_speed(ball, SLOW_BALL_DECREASE);
+            }
             PowerUpType::Sticky => {
                 ball.sticky = true;
                 player.color = glm::vec3(1.0, 0.5, 1.0);
@@ -526,53 +952,107 @@ impl Game {
                 ball.object.color = glm::vec3(1.0, 0.5, 0.5);
             }
             PowerUpType::PadSizeIncrease => {
-                player.size.x += 50.0;
+                let increase = PAD_SIZE_INCREASE
+                    .min(MAX_PLAYER_WIDTH - player.size.x)
+                    .max(0.0);
+                Self::resize_player(player, increase, self.graphics.width);
+                powerup.applied = increase;
+            }
+            PowerUpType::Shrink => {
+                let decrease = PAD_SIZE_DECREASE
+                    .min(player.size.x - MIN_PLAYER_WIDTH)
+                    .max(0.0);
+                Self::resize_player(player, -decrease, self.graphics.width);
+                // stored negated so undoing it is the same as for the size increase
+                powerup.applied = -decrease;
             }
             PowerUpType::Confuse => {
-                if !effects.chaos {
-                    effects.confuse = true;
+                if !effects.is_enabled("chaos") {
+                    effects.fade("confuse", true, EFFECT_FADE_TIME);
                 }
             }
             PowerUpType::Chaos => {
-                if !effects.confuse {
-                    effects.chaos = true;
+                if !effects.is_enabled("confuse") {
+                    effects.fade("chaos", true, EFFECT_FADE_TIME);
                 }
             }
+            PowerUpType::Laser => {
+                self.laser_cooldown = 0.0;
+            }
+            PowerUpType::ExtraLife => {
+                self.lives = (self.lives + 1).min(MAX_LIVES);
+            }
+            PowerUpType::Shield => {
+                self.shield.destroyed = false;
+            }
+            PowerUpType::Magnet => {}
+        }
+    }
+
+    /// Scales the ball speed while keeping it between the minimum and maximum speed,
+    /// returning the factor that was actually applied
+    fn scale_ball_speed(ball: &mut Ball, factor: f32) -> f32 {
+        let speed = glm::length(&ball.object.velocity);
+        if speed <= 0.0 {
+            return 1.0;
         }
+        let target = (speed * factor).clamp(MIN_BALL_SPEED.min(speed), MAX_BALL_SPEED.max(speed));
+        let applied = target / speed;
+        ball.object.velocity *= applied;
+        applied
+    }
+
+    /// Grows or shrinks the paddle around its center while keeping it inside the window
+    fn resize_player(player: &mut GameObject, amount: f32, window_width: u32) {
+        player.size.x += amount;
+        player.position.x -= amount / 2.0;
+        player.position.x = player
+            .position
+            .x
+            .clamp(0.0, (window_width as f32 - player.size.x).max(0.0));
     }
 
     fn do_collisions(&mut self) {
-        for brick in &mut self.levels[self.level as usize].bricks {
+        let level = &mut self.levels[self.level as usize];
+        // center of the last brick destroyed this frame
+        let mut destroyed = None;
+        for brick in &mut level.bricks {
             if !brick.destroyed {
                 let collision = check_collision_circle(&self.ball, brick);
 
                 if collision.0 {
                     if !brick.is_solid {
                         brick.destroyed = true;
-                        Self::spawn_powerups(&mut self.powerups, brick);
-                        // TODO: Sound should have been preloaded and just played here.
-                        self.audio_manager
-                            .play(
-                                StaticSoundData::from_file(
-                                    "resources/audio/bleep.mp3",
-                                    StaticSoundSettings::new().volume(0.5),
-                                )
-                                .unwrap(),
-                            )
-                            .unwrap();
+                        self.particle_generator.emit(
+                            "brick_shatter",
+                            brick.position,
+                            brick.size,
+                            brick.color,
+                        );
+                        Self::spawn_powerups(
+                            &mut self.powerups,
+                            &self.powerup_registry,
+                            &level.powerup_weights,
+                            brick,
+                        );
+                        self.audio.play_sound_at(
+                            "bleep",
+                            brick.position.x + brick.size.x / 2.0,
+                            color_semitones(&brick.color) + combo_semitones(self.combo),
+                        );
+                        self.combo += 1;
+                        destroyed = Some(brick.position + brick.size / 2.0);
                     } else {
-                        self.shake_time = 0.05;
-                        self.effects.shake = true;
-                        // TODO: Sound should have been preloaded and just played.
-                        self.audio_manager
-                            .play(
-                                StaticSoundData::from_file(
-                                    "resources/audio/solid.wav",
-                                    StaticSoundSettings::new().volume(0.5),
-                                )
-                                .unwrap(),
-                            )
-                            .unwrap();
+                        let camera = &mut self.graphics.camera;
+                        camera.add_trauma(SOLID_HIT_TRAUMA);
+                        if let Some(direction) = self.ball.object.velocity.try_normalize(0.0) {
+                            camera.kick(direction * SOLID_HIT_KICK);
+                        }
+                        self.audio.play_sound_at(
+                            "solid",
+                            brick.position.x + brick.size.x / 2.0,
+                            0.0,
+                        );
                     }
 
                     let dir = collision.1;
@@ -615,33 +1095,86 @@ impl Game {
             }
         }
 
+        // laser bolts destroy regular bricks and get stopped by solid ones
+        for laser in &mut self.lasers {
+            for brick in &mut level.bricks {
+                if brick.destroyed || !check_collision_box(laser, brick) {
+                    continue;
+                }
+                laser.destroyed = true;
+                if !brick.is_solid {
+                    brick.destroyed = true;
+                    self.particle_generator.emit(
+                        "brick_shatter",
+                        brick.position,
+                        brick.size,
+                        brick.color,
+                    );
+                    Self::spawn_powerups(
+                        &mut self.powerups,
+                        &self.powerup_registry,
+                        &level.powerup_weights,
+                        brick,
+                    );
+                    self.audio.play_sound_at(
+                        "bleep",
+                        brick.position.x + brick.size.x / 2.0,
+                        color_semitones(&brick.color),
+                    );
+                    destroyed = Some(brick.position + brick.size / 2.0);
+                }
+                break;
+            }
+        }
+
+        if let Some(center) = destroyed {
+            if level.is_completed() {
+                self.graphics
+                    .camera
+                    .focus(center, FINAL_BRICK_ZOOM, FINAL_BRICK_FOCUS_TIME);
+            }
+        }
+
+        // the shield bounces the ball back up once before it breaks
+        if !self.shield.destroyed
+            && self.ball.object.velocity.y > 0.0
+            && self.ball.position().y + self.ball.radius * 2.0 >= self.shield.position.y
+        {
+            self.ball.object.velocity.y = -self.ball.object.velocity.y;
+            self.ball.object.position.y = self.shield.position.y - self.ball.radius * 2.0;
+            self.shield.destroyed = true;
+            // a broken barrier ends the power-up so its countdown leaves the HUD as well
+            for powerup in &mut self.powerups {
+                if powerup.r#type == PowerUpType::Shield {
+                    powerup.activated = false;
+                }
+            }
+            self.audio
+                .play_sound_at("solid", self.ball.position().x + self.ball.radius, 0.0);
+        }
+
         // also check collisions on PowerUps and if so, activate them
-        for powerup in &mut self.powerups {
-            if !powerup.object.destroyed {
+        for i in 0..self.powerups.len() {
+            if !self.powerups[i].object.destroyed {
                 // first check if powerup passed bottom edge, if so: keep as inactive and destroy
-                if powerup.object.position.y >= self.graphics.height as f32 {
-                    powerup.object.destroyed = true;
+                if self.powerups[i].object.position.y >= self.graphics.height as f32 {
+                    self.powerups[i].object.destroyed = true;
                 }
 
-                if check_collision_box(&self.player, &powerup.object) {
-                    Self::activate_powerup(
-                        &mut self.ball,
-                        &mut self.player,
-                        powerup,
-                        &mut self.effects,
+                if check_collision_box(&self.player, &self.powerups[i].object) {
+                    self.powerups[i].object.destroyed = true;
+                    self.collect_powerup(i);
+                    self.particle_generator.emit(
+                        "powerup_collected",
+                        self.powerups[i].object.position + self.powerups[i].object.size / 2.0,
+                        self.powerups[i].object.size,
+                        self.powerups[i].object.color,
+                    );
+                    self.audio.play_sound_at(
+                        "powerup",
+                        self.powerups[i].object.position.x + self.powerups[i].object.size.x / 2.0,
+                        0.0,
                     );
-                    powerup.object.destroyed = true;
-                    powerup.activated = true;
-                    // TODO: Sound should have been preloaded and just played.
-                    self.audio_manager
-                        .play(
-                            StaticSoundData::from_file(
-                                "resources/audio/powerup.wav",
-                                StaticSoundSettings::new().volume(0.5),
-                            )
-                            .unwrap(),
-                        )
-                        .unwrap();
                 }
             }
         }
@@ -663,97 +1196,145 @@ impl Game {
 
             // if Sticky powerup is activated, also stick ball to paddle once new velocity vectors were calculated
             self.ball.stuck = self.ball.sticky;
+
+            self.audio
+                .play_sound_at("paddle", self.ball.position().x + self.ball.radius, 0.0);
+            self.particle_generator.emit(
+                "paddle_hit",
+                self.ball.position() + glm::vec2(self.ball.radius, self.ball.radius * 2.0),
+                self.ball.object.size,
+                glm::vec3(1.0, 1.0, 1.0),
+            );
+            self.combo = 0;
         }
     }
 }
 
-fn load_shaders(shader_manager: &mut ShaderManager) {
-    shader_manager.load_shader(
-        Path::new("shaders/particle.vs"),
-        Path::new("shaders/particle.frag"),
-        None,
-        "particle".to_string(),
-    );
-
-    shader_manager.load_shader(
-        Path::new("shaders/post_processing.vs"),
-        Path::new("shaders/post_processing.frag"),
-        None,
-        "postprocessing".to_string(),
-    );
+fn load_shaders(shader_manager: &mut ShaderManager, report: &mut AssetReport) {
+    shader_manager.define("KERNEL_SIZE", KERNEL_SIZE);
+    for (vertex, fragment, name) in [
+        ("shaders/particle.vs", "shaders/particle.frag", "particle"),
+        ("shaders/text_2d.vs", "shaders/text_2d.frag", "text"),
+        ("shaders/text_2d.vs", "shaders/text_sdf.frag", "text_sdf"),
+    ] {
+        report.record(shader_manager.load_shader(
+            Path::new(vertex),
+            Path::new(fragment),
+            None,
+            name.to_string(),
+        ));
+    }
 
-    shader_manager.load_shader(
-        Path::new("shaders/text_2d.vs"),
-        Path::new("shaders/text_2d.frag"),
-        None,
-        "text".to_string(),
-    );
+    // every post processing pass draws a quad over the screen with the same vertex shader
+    for name in [
+        "chaos",
+        "confuse",
+        "shake",
+        "bloom_bright",
+        "bloom_blur",
+        "bloom_composite",
+        "crt",
+    ] {
+        report.record(shader_manager.load_shader(
+            Path::new("shaders/effects/effect.vs"),
+            &Path::new("shaders/effects").join(format!("{}.frag", name)),
+            None,
+            name.to_string(),
+        ));
+    }
 }
 
-fn load_textures(texture_manager: &mut TextureManager) {
-    texture_manager.load_texture(
-        Path::new("resources/textures/background.jpg"),
-        false,
-        "background",
+fn load_sounds(sound_manager: &mut SoundManager) {
+    sound_manager.load_sound(
+        Path::new("resources/audio/bleep.mp3"),
+        StaticSoundSettings::new().volume(0.5),
+        "bleep",
     );
 
-    texture_manager.load_texture(
-        Path::new("resources/textures/awesomeface.png"),
-        true,
-        "ball",
+    sound_manager.load_sound(
+        Path::new("resources/audio/solid.wav"),
+        StaticSoundSettings::new().volume(0.5),
+        "solid",
     );
 
-    texture_manager.load_texture(Path::new("resources/textures/block.png"), false, "block");
-
-    texture_manager.load_texture(
-        Path::new("resources/textures/block_solid.png"),
-        false,
-        "block_solid",
+    sound_manager.load_sound(
+        Path::new("resources/audio/powerup.wav"),
+        StaticSoundSettings::new().volume(0.5),
+        "powerup",
     );
 
-    texture_manager.load_texture(Path::new("resources/textures/paddle.png"), true, "paddle");
-
-    texture_manager.load_texture(
-        Path::new("resources/textures/particle.png"),
-        true,
-        "particle",
+    sound_manager.load_sound(
+        Path::new("resources/audio/bleep.wav"),
+        StaticSoundSettings::new().volume(0.5),
+        "paddle",
     );
+}
 
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_speed.png"),
-        true,
-        "powerup_speed",
+fn load_music(music_player: &mut MusicPlayer) {
+    let main_theme = MusicSource::Track("resources/music/levels/level_drive.wav".into());
+    music_player.assign(MusicCue::Menu, main_theme.clone());
+    music_player.assign(MusicCue::Level(0), main_theme.clone());
+    music_player.assign(MusicCue::Level(1), main_theme);
+    music_player.assign(
+        MusicCue::Level(2),
+        MusicSource::Playlist("resources/music/levels".into()),
     );
-
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_sticky.png"),
-        true,
-        "powerup_sticky",
+    music_player.assign(
+        MusicCue::Level(3),
+        MusicSource::Playlist("resources/music/levels".into()),
     );
-
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_increase.png"),
-        true,
-        "powerup_increase",
+    music_player.assign(
+        MusicCue::Win,
+        MusicSource::Track("resources/music/octoball_higher_volume.wav".into()),
     );
+}
 
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_confuse.png"),
-        true,
-        "powerup_confuse",
-    );
+fn load_textures(texture_manager: &mut TextureManager, report: &mut AssetReport) {
+    // the background and the particle texture are drawn on their own, so they are not
+    // worth packing into the atlas
+    report.record(texture_manager.load_texture(
+        Path::new("resources/textures/background.jpg"),
+        false,
+        "background",
+    ));
 
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_chaos.png"),
+    report.record(texture_manager.load_texture(
+        Path::new("resources/textures/particle.png"),
         true,
-        "powerup_chaos",
-    );
+        "particle",
+    ));
+
+    // plain white texture for drawing solid colored quads
+    texture_manager.queue_atlas_data(1, 1, &[255, 255, 255, 255], "white");
+
+    for (file, name) in [
+        ("resources/textures/awesomeface.png", "ball"),
+        ("resources/textures/block.png", "block"),
+        ("resources/textures/block_solid.png", "block_solid"),
+        ("resources/textures/paddle.png", "paddle"),
+        ("resources/textures/powerup_speed.png", "powerup_speed"),
+        ("resources/textures/powerup_sticky.png", "powerup_sticky"),
+        (
+            "resources/textures/powerup_increase.png",
+            "powerup_increase",
+        ),
+        ("resources/textures/powerup_confuse.png", "powerup_confuse"),
+        ("resources/textures/powerup_chaos.png", "powerup_chaos"),
+        (
+            "resources/textures/powerup_passthrough.png",
+            "powerup_passthrough",
+        ),
+        ("resources/textures/powerup_laser.png", "powerup_laser"),
+        ("resources/textures/powerup_shrink.png", "powerup_shrink"),
+        ("resources/textures/powerup_slow.png", "powerup_slow"),
+        ("resources/textures/powerup_life.png", "powerup_life"),
+        ("resources/textures/powerup_shield.png", "powerup_shield"),
+        ("resources/textures/powerup_magnet.png", "powerup_magnet"),
+    ] {
+        report.record(texture_manager.queue_atlas_texture(Path::new(file), name));
+    }
 
-    texture_manager.load_texture(
-        Path::new("resources/textures/powerup_passthrough.png"),
-        true,
-        "powerup_passthrough",
-    );
+    texture_manager.build_atlases(ATLAS_SIZE, ATLAS_PADDING, true);
 }
 
 fn load_levels(
@@ -763,7 +1344,7 @@ fn load_levels(
     texture_manager: &TextureManager,
 ) {
     // load levels
-    let mut one = GameLevel { bricks: vec![] };
+    let mut one = GameLevel::new();
     one.load(
         Path::new("resources/levels/one.lvl"),
         width,
@@ -771,7 +1352,7 @@ fn load_levels(
         texture_manager,
     );
     levels.push(one);
-    let mut two = GameLevel { bricks: vec![] };
+    let mut two = GameLevel::new();
     two.load(
         Path::new("resources/levels/two.lvl"),
         width,
@@ -779,7 +1360,7 @@ fn load_levels(
         texture_manager,
     );
     levels.push(two);
-    let mut three = GameLevel { bricks: vec![] };
+    let mut three = GameLevel::new();
     three.load(
         Path::new("resources/levels/three.lvl"),
         width,
@@ -787,7 +1368,7 @@ fn load_levels(
         texture_manager,
     );
     levels.push(three);
-    let mut four = GameLevel { bricks: vec![] };
+    let mut four = GameLevel::new();
     four.load(
         Path::new("resources/levels/four.lvl"),
         width,
@@ -871,8 +1452,3 @@ fn vector_direction(target: glm::Vec2) -> Direction {
 
     best_match
 }
-
-fn should_spawn(chance: u32) -> bool {
-    let random_val = random::<u32>() % chance;
-    random_val == 0
-}
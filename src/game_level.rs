@@ -1,19 +1,30 @@
 #![allow(dead_code)]
-use std::{fs, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
 use nalgebra_glm as glm;
 
 use crate::{
-    game_object::GameObject, graphics::sprite_renderer::SpriteRenderer,
+    game_object::GameObject,
+    graphics::sprite_batch::{layer, SpriteBatch},
     graphics::texture_manager::TextureManager,
+    powerup::{load_weight_overrides, PowerUpType},
 };
 
 #[derive(Debug)]
 pub struct GameLevel {
     pub bricks: Vec<GameObject>,
+    // power-up weights that differ from the global definitions for this level
+    pub powerup_weights: HashMap<PowerUpType, u32>,
 }
 
 impl GameLevel {
+    pub fn new() -> Self {
+        Self {
+            bricks: vec![],
+            powerup_weights: HashMap::new(),
+        }
+    }
+
     pub fn load(
         &mut self,
         file: &Path,
@@ -23,6 +34,7 @@ impl GameLevel {
     ) {
         // clear old data
         self.bricks.clear();
+        self.powerup_weights = load_weight_overrides(&file.with_extension("powerups"));
         let mut tile_data = vec![];
 
         let contents = fs::read_to_string(file).expect("Should have been able to read the file");
@@ -50,11 +62,11 @@ impl GameLevel {
         }
     }
 
-    pub fn draw(&mut self, renderer: &mut SpriteRenderer, texture_manager: &TextureManager) {
+    pub fn draw(&mut self, renderer: &mut SpriteBatch, texture_manager: &TextureManager) {
         for tile in self.bricks.iter_mut() {
             if !tile.destroyed {
                 let texture = texture_manager.get_texture(tile.sprite_id());
-                tile.draw(renderer, texture);
+                tile.draw(renderer, texture, layer::LEVEL);
             }
         }
     }
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 use nalgebra_glm as glm;
 
-use crate::{graphics::sprite_renderer::SpriteRenderer, graphics::texture::Texture2D};
+use crate::{graphics::sprite_batch::SpriteBatch, graphics::texture::Texture2D};
 
 #[derive(Debug, Clone)]
 pub struct GameObject {
@@ -52,8 +52,15 @@ impl GameObject {
         }
     }
 
-    pub fn draw(&self, renderer: &mut SpriteRenderer, sprite: &Texture2D) {
-        renderer.draw_sprite(sprite, self.position, self.size, self.rotation, self.color);
+    pub fn draw(&self, renderer: &mut SpriteBatch, sprite: &Texture2D, layer: i32) {
+        renderer.draw_sprite(
+            sprite,
+            self.position,
+            self.size,
+            self.rotation,
+            self.color,
+            layer,
+        );
     }
 
     pub fn sprite_id(&self) -> &String {
@@ -0,0 +1,118 @@
+#![allow(dead_code)]
+use std::{error::Error, fmt, io, path::PathBuf};
+
+/// Why a texture or shader could not be loaded
+#[derive(Debug)]
+pub enum AssetError {
+    /// The file could not be read
+    Io { path: PathBuf, error: io::Error },
+    /// The file is not an image that can be decoded
+    Image {
+        path: PathBuf,
+        error: image::ImageError,
+    },
+    /// A shader stage did not compile, `log` is the compile log of the driver
+    ShaderCompile {
+        path: PathBuf,
+        stage: &'static str,
+        log: String,
+    },
+    /// The shader stages compiled but could not be linked into a program
+    ShaderLink { name: String, log: String },
+    /// The source of a shader contains a NUL byte
+    InvalidSource { path: PathBuf },
+    /// FreeType could not open the font file
+    Font {
+        path: PathBuf,
+        error: freetype::Error,
+    },
+    /// Nothing was loaded under the name
+    NotFound { kind: &'static str, name: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io { path, error } => {
+                write!(f, "failed to read {}: {}", path.display(), error)
+            }
+            AssetError::Image { path, error } => {
+                write!(f, "failed to decode image {}: {}", path.display(), error)
+            }
+            AssetError::ShaderCompile { path, stage, log } => write!(
+                f,
+                "failed to compile {} shader {}:\n{}",
+                stage,
+                path.display(),
+                log.trim_end()
+            ),
+            AssetError::ShaderLink { name, log } => {
+                write!(f, "failed to link shader {}:\n{}", name, log.trim_end())
+            }
+            AssetError::InvalidSource { path } => {
+                write!(f, "shader source {} contains a NUL byte", path.display())
+            }
+            AssetError::Font { path, error } => {
+                write!(f, "failed to load font {}: {}", path.display(), error)
+            }
+            AssetError::NotFound { kind, name } => write!(f, "no {} named {}", kind, name),
+        }
+    }
+}
+
+impl Error for AssetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AssetError::Io { error, .. } => Some(error),
+            AssetError::Image { error, .. } => Some(error),
+            AssetError::Font { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Collects every asset that failed to load, so they can all be reported at once instead of
+/// stopping at the first one
+#[derive(Debug, Default)]
+pub struct AssetReport {
+    problems: Vec<AssetError>,
+}
+
+impl AssetReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the error of a failed load and passes on the value of a successful one
+    pub fn record<T>(&mut self, result: Result<T, AssetError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.problems.push(error);
+                None
+            }
+        }
+    }
+
+    pub fn problems(&self) -> &[AssetError] {
+        &self.problems
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Prints every problem, missing assets are replaced by fallbacks so the game still runs
+    pub fn print(&self) {
+        if self.problems.is_empty() {
+            return;
+        }
+        eprintln!(
+            "ERROR::ASSETS: {} asset(s) failed to load and were replaced by fallbacks",
+            self.problems.len()
+        );
+        for problem in &self.problems {
+            eprintln!("  - {}", problem.to_string().replace('\n', "\n    "));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+use nalgebra_glm as glm;
+
+use super::{
+    post_processor::{draw_quad, RenderTarget},
+    shader::Shader,
+    shader_manager::ShaderManager,
+    texture::Texture2D,
+};
+
+// the glow is blurred at a fraction of the screen resolution, which is cheaper and spreads
+// it further with the same blur
+const DOWNSAMPLE: i32 = 2;
+pub const DEFAULT_THRESHOLD: f32 = 0.8;
+pub const DEFAULT_INTENSITY: f32 = 0.8;
+pub const DEFAULT_BLUR_PASSES: u32 = 3;
+
+/// Makes the bright parts of the scene glow. They are extracted from the scene, blurred at
+/// reduced resolution and added back on top of it.
+#[derive(Debug)]
+pub struct Bloom {
+    /// Brightness from 0 to 1 above which parts of the scene start to glow
+    pub threshold: f32,
+    /// How strongly the glow is added to the scene, 0 turns bloom off
+    pub intensity: f32,
+    /// How many times the glow is blurred horizontally and vertically, more spreads it further
+    pub blur_passes: u32,
+    bright_shader: Shader,
+    blur_shader: Shader,
+    composite_shader: Shader,
+    width: i32,
+    height: i32,
+    targets: [RenderTarget; 2],
+}
+
+impl Bloom {
+    pub fn new(shader_manager: &ShaderManager, width: i32, height: i32) -> Self {
+        let width = (width / DOWNSAMPLE).max(1);
+        let height = (height / DOWNSAMPLE).max(1);
+
+        let composite_shader = shader_manager.get_shader("bloom_composite").clone();
+        composite_shader.use_program();
+        composite_shader.set_int("scene", 0);
+        composite_shader.set_int("bloom", 1);
+
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            intensity: DEFAULT_INTENSITY,
+            blur_passes: DEFAULT_BLUR_PASSES,
+            bright_shader: shader_manager.get_shader("bloom_bright").clone(),
+            blur_shader: shader_manager.get_shader("bloom_blur").clone(),
+            composite_shader,
+            width,
+            height,
+            // clamped, so the glow does not bleed in from the opposite edge of the screen
+            targets: [
+                RenderTarget::new(width, height, gl::CLAMP_TO_EDGE),
+                RenderTarget::new(width, height, gl::CLAMP_TO_EDGE),
+            ],
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    /// Extracts the parts of the scene brighter than the threshold and blurs them, returning
+    /// the texture with the glow
+    pub(super) fn glow(&self, scene: &Texture2D, vao: u32) -> &Texture2D {
+        let texel_size = glm::vec2(1.0 / self.width as f32, 1.0 / self.height as f32);
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            // bright pass, which also scales the scene down
+            self.bright_shader.use_program();
+            self.bright_shader.set_int("scene", 0);
+            self.bright_shader.set_float("threshold", self.threshold);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.targets[0].fbo);
+            scene.bind();
+            draw_quad(vao);
+
+            // separable gaussian blur, going back and forth between the two targets
+            self.blur_shader.use_program();
+            self.blur_shader.set_int("scene", 0);
+            self.blur_shader.set_vec2("texelSize", &texel_size);
+            for _ in 0..self.blur_passes {
+                for (direction, from, to) in
+                    [(glm::vec2(1.0, 0.0), 0, 1), (glm::vec2(0.0, 1.0), 1, 0)]
+                {
+                    self.blur_shader.set_vec2("direction", &direction);
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, self.targets[to].fbo);
+                    self.targets[from].texture.bind();
+                    draw_quad(vao);
+                }
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        &self.targets[0].texture
+    }
+
+    /// Shader that adds the glow in texture unit 1 to the scene in texture unit 0, in use
+    pub(super) fn composite_shader(&self) -> &Shader {
+        self.composite_shader.use_program();
+        self.composite_shader.set_float("intensity", self.intensity);
+        &self.composite_shader
+    }
+}
@@ -0,0 +1,258 @@
+#![allow(dead_code)]
+use nalgebra_glm as glm;
+
+// how fast the view eases towards where it should be, higher is snappier
+const FOLLOW_SPEED: f32 = 8.0;
+// trauma lost per second, so a full shake is over after a second
+const TRAUMA_DECAY: f32 = 1.0;
+// how far a full shake moves and turns the view, in pixels and radians
+const MAX_SHAKE_OFFSET: f32 = 12.0;
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+// how many times per second the shake changes direction, roughly
+const SHAKE_FREQUENCY: f32 = 20.0;
+// how fast a kick springs back, higher is shorter
+const KICK_RECOVERY: f32 = 12.0;
+
+/// 2D camera over the playfield. The sprite, particle and world text shaders get its `view`
+/// matrix, while `projection` stays a fixed orthographic projection over the window, so
+/// `view` alone pans, zooms and shakes everything in the world.
+///
+/// Shake is driven by trauma from 0 to 1 which decays over time, the view moves by the
+/// square of it so small hits barely register and big ones really shake. Kicks push the
+/// view in a direction and spring back.
+#[derive(Debug)]
+pub struct Camera {
+    width: f32,
+    height: f32,
+    /// Point of the world in the middle of the screen
+    pub center: glm::Vec2,
+    /// Scale of the world, above 1 zooms in
+    pub zoom: f32,
+    // where the camera eases to when it is not focusing on anything
+    rest_center: glm::Vec2,
+    rest_zoom: f32,
+    // point, zoom and seconds left of a focus that temporarily overrides the rest position
+    focus: Option<(glm::Vec2, f32, f32)>,
+    trauma: f32,
+    kick: glm::Vec2,
+    // drives the shake, only advances in `update` so the same updates always shake the same
+    time: f32,
+}
+
+impl Camera {
+    /// Camera showing the whole window of `width` by `height` pixels
+    pub fn new(width: u32, height: u32) -> Self {
+        let center = glm::vec2(width as f32 / 2.0, height as f32 / 2.0);
+        Self {
+            width: width as f32,
+            height: height as f32,
+            center,
+            zoom: 1.0,
+            rest_center: center,
+            rest_zoom: 1.0,
+            focus: None,
+            trauma: 0.0,
+            kick: glm::vec2(0.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    /// Orthographic projection over the window, with the origin in the top left corner
+    pub fn projection(&self) -> glm::Mat4 {
+        glm::ortho(0.0, self.width, self.height, 0.0, -1.0, 1.0)
+    }
+
+    /// Moves the world so `center` is in the middle of the screen, scaled by `zoom` and
+    /// moved and turned by the shake
+    pub fn view(&self) -> glm::Mat4 {
+        let shake = self.shake();
+        let offset = glm::vec2(wave(self.time, 0.0), wave(self.time, 10.0))
+            * (MAX_SHAKE_OFFSET * shake)
+            + self.kick;
+        let angle = wave(self.time, 20.0) * MAX_SHAKE_ANGLE * shake;
+
+        let screen_center = glm::vec3(self.width / 2.0, self.height / 2.0, 0.0);
+        let view = glm::translate(&glm::Mat4::identity(), &screen_center);
+        let view = glm::rotate_z(&view, angle);
+        let view = glm::scale(&view, &glm::vec3(self.zoom, self.zoom, 1.0));
+        let eye = self.center + offset;
+        glm::translate(&view, &glm::vec3(-eye.x, -eye.y, 0.0))
+    }
+
+    /// Moves the camera to `center` and `zoom` right away, and keeps it there
+    pub fn set(&mut self, center: glm::Vec2, zoom: f32) {
+        self.center = center;
+        self.zoom = zoom;
+        self.rest_center = center;
+        self.rest_zoom = zoom;
+    }
+
+    /// Eases the camera to `center` and `zoom` and keeps it there
+    pub fn move_to(&mut self, center: glm::Vec2, zoom: f32) {
+        self.rest_center = center;
+        self.rest_zoom = zoom;
+    }
+
+    /// Eases the camera by `delta` pixels
+    pub fn pan(&mut self, delta: glm::Vec2) {
+        self.rest_center += delta;
+    }
+
+    /// Eases the camera back to showing the whole window and stops any shake
+    pub fn reset(&mut self) {
+        self.move_to(glm::vec2(self.width / 2.0, self.height / 2.0), 1.0);
+        self.focus = None;
+        self.trauma = 0.0;
+        self.kick = glm::vec2(0.0, 0.0);
+    }
+
+    /// Eases the camera to `point` at `zoom` for `duration` seconds, after which it eases
+    /// back to where it was
+    pub fn focus(&mut self, point: glm::Vec2, zoom: f32, duration: f32) {
+        self.focus = Some((point, zoom, duration));
+    }
+
+    /// Adds trauma from 0 to 1, trauma above 1 is capped
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Pushes the view `amount` pixels in a direction, it springs back on its own
+    pub fn kick(&mut self, amount: glm::Vec2) {
+        self.kick += amount;
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// How strongly the camera shakes, from 0 to 1
+    pub fn shake(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.kick *= (-KICK_RECOVERY * dt).exp();
+
+        let (center, zoom) = match &mut self.focus {
+            Some((point, zoom, remaining)) => {
+                *remaining -= dt;
+                (*point, *zoom)
+            }
+            None => (self.rest_center, self.rest_zoom),
+        };
+        if self.focus.is_some_and(|(_, _, remaining)| remaining <= 0.0) {
+            self.focus = None;
+        }
+
+        // frame rate independent easing, covers the same part of the distance every second
+        let follow = 1.0 - (-FOLLOW_SPEED * dt).exp();
+        self.center += (center - self.center) * follow;
+        self.zoom += (zoom - self.zoom) * follow;
+    }
+}
+
+/// Smooth pseudo random value from -1 to 1, a few sine waves that never quite line up.
+/// Different seeds give unrelated waves.
+fn wave(time: f32, seed: f32) -> f32 {
+    let t = time * SHAKE_FREQUENCY + seed;
+    ((t * 1.0).sin() + (t * 2.31 + 1.7).sin() * 0.5 + (t * 4.13 + 3.1).sin() * 0.25) / 1.75
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(camera: &mut Camera, seconds: f32) {
+        for _ in 0..(seconds / DT).round() as u32 {
+            camera.update(DT);
+        }
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {} but got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn view_puts_center_in_the_middle() {
+        let mut camera = Camera::new(800, 600);
+        camera.set(glm::vec2(100.0, 50.0), 2.0);
+        let view = camera.view();
+
+        let center = view * glm::vec4(100.0, 50.0, 0.0, 1.0);
+        assert_near(center.x, 400.0);
+        assert_near(center.y, 300.0);
+        // zoomed in twice, so the world moves twice as far on screen
+        let right = view * glm::vec4(110.0, 50.0, 0.0, 1.0);
+        assert_near(right.x, 420.0);
+    }
+
+    #[test]
+    fn trauma_decays_linearly_and_shake_with_its_square() {
+        let mut camera = Camera::new(800, 600);
+        camera.add_trauma(0.7);
+        camera.add_trauma(0.7);
+        assert_near(camera.trauma(), 1.0);
+
+        camera.update(0.5);
+        assert_near(camera.trauma(), 0.5);
+        assert_near(camera.shake(), 0.25);
+
+        camera.update(1.0);
+        assert_near(camera.trauma(), 0.0);
+        assert_near(camera.shake(), 0.0);
+    }
+
+    #[test]
+    fn kick_springs_back() {
+        let mut camera = Camera::new(800, 600);
+        camera.kick(glm::vec2(10.0, 0.0));
+
+        camera.update(0.1);
+        assert_near(camera.kick.x, 10.0 * (-KICK_RECOVERY * 0.1).exp());
+        run(&mut camera, 1.0);
+        assert!(camera.kick.norm() < 0.01);
+    }
+
+    #[test]
+    fn focus_returns_to_rest() {
+        let mut camera = Camera::new(800, 600);
+        let point = glm::vec2(100.0, 100.0);
+        camera.focus(point, 2.0, 0.5);
+
+        run(&mut camera, 0.4);
+        assert!(glm::distance(&camera.center, &point) < 20.0);
+        assert!(camera.zoom > 1.9);
+
+        run(&mut camera, 2.0);
+        assert!(camera.focus.is_none());
+        assert!(glm::distance(&camera.center, &glm::vec2(400.0, 300.0)) < 0.1);
+        assert_near(camera.zoom, 1.0);
+    }
+
+    #[test]
+    fn reset_stops_shake_and_focus() {
+        let mut camera = Camera::new(800, 600);
+        camera.move_to(glm::vec2(0.0, 0.0), 3.0);
+        camera.add_trauma(1.0);
+        camera.kick(glm::vec2(5.0, 5.0));
+        camera.focus(glm::vec2(10.0, 10.0), 2.0, 1.0);
+
+        camera.reset();
+        assert_near(camera.trauma(), 0.0);
+        assert_eq!(camera.kick, glm::vec2(0.0, 0.0));
+        assert!(camera.focus.is_none());
+        run(&mut camera, 2.0);
+        assert!(glm::distance(&camera.center, &glm::vec2(400.0, 300.0)) < 0.1);
+        assert_near(camera.zoom, 1.0);
+    }
+}
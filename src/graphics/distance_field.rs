@@ -0,0 +1,177 @@
+#![allow(dead_code)]
+
+const INF: f64 = 1e20;
+
+/// Turns a coverage bitmap into a signed distance field with `spread` pixels of border on
+/// every side. A value of 128 lies on the edge of the shape, values above it are inside
+/// and every step of `64 / spread` is one pixel further away from the edge.
+///
+/// Returns the distance field together with its width and height.
+pub fn distance_field(
+    bitmap: &[u8],
+    width: usize,
+    height: usize,
+    pitch: usize,
+    spread: usize,
+) -> (Vec<u8>, usize, usize) {
+    let field_width = width + 2 * spread;
+    let field_height = height + 2 * spread;
+    let size = field_width * field_height;
+
+    // squared distances to the closest pixel inside and outside of the shape
+    let mut to_inside = vec![INF; size];
+    let mut to_outside = vec![0.0; size];
+    for y in 0..height {
+        for x in 0..width {
+            if bitmap[y * pitch + x] >= 128 {
+                let index = (y + spread) * field_width + x + spread;
+                to_inside[index] = 0.0;
+                to_outside[index] = INF;
+            }
+        }
+    }
+    transform_2d(&mut to_inside, field_width, field_height);
+    transform_2d(&mut to_outside, field_width, field_height);
+
+    let field = to_inside
+        .iter()
+        .zip(&to_outside)
+        .map(|(inside, outside)| {
+            // distances are measured between pixel centres, the edge lies half a pixel out
+            let distance = if *inside == 0.0 {
+                outside.sqrt() - 0.5
+            } else {
+                0.5 - inside.sqrt()
+            };
+            (128.0 + distance * 64.0 / spread as f64).clamp(0.0, 255.0) as u8
+        })
+        .collect();
+    (field, field_width, field_height)
+}
+
+/// Squared euclidean distance transform of a grid, first along every column and then along
+/// every row
+fn transform_2d(grid: &mut [f64], width: usize, height: usize) {
+    let length = width.max(height);
+    let mut f = vec![0.0; length];
+    let mut d = vec![0.0; length];
+    let mut v = vec![0; length];
+    let mut z = vec![0.0; length + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = grid[y * width + x];
+        }
+        transform_1d(&f[..height], &mut d, &mut v, &mut z);
+        for y in 0..height {
+            grid[y * width + x] = d[y];
+        }
+    }
+
+    for y in 0..height {
+        f[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+        transform_1d(&f[..width], &mut d, &mut v, &mut z);
+        grid[y * width..(y + 1) * width].copy_from_slice(&d[..width]);
+    }
+}
+
+/// One dimensional squared distance transform by Felzenszwalb and Huttenlocher, finding the
+/// lower envelope of the parabolas rooted at every sample
+fn transform_1d(f: &[f64], d: &mut [f64], v: &mut [usize], z: &mut [f64]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64)
+    };
+
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -INF;
+    z[1] = INF;
+    for q in 1..n {
+        let mut s = intersection(q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersection(q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+
+    k = 0;
+    for (q, distance) in d.iter_mut().enumerate().take(n) {
+        while z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - v[k] as f64;
+        *distance = offset * offset + f[v[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPREAD: usize = 8;
+    const STEP: f64 = 64.0 / SPREAD as f64;
+
+    /// A filled 10x10 square, with rows padded to a pitch of 12 by bytes that are not part
+    /// of the bitmap
+    fn square() -> (Vec<u8>, usize, usize) {
+        let mut bitmap = vec![255; 12 * 10];
+        for row in bitmap.chunks_mut(12) {
+            row[10] = 0;
+            row[11] = 0;
+        }
+        distance_field(&bitmap, 10, 10, 12, SPREAD)
+    }
+
+    #[test]
+    fn edge_of_a_filled_square_is_128() {
+        let (field, width, height) = square();
+        assert_eq!((width, height), (10 + 2 * SPREAD, 10 + 2 * SPREAD));
+
+        // pixel centres lie half a pixel to either side of the edge, so the edge itself is
+        // halfway between the last pixel inside and the first one outside
+        let row = (SPREAD + 5) * width;
+        for (inside, outside) in [(SPREAD, SPREAD - 1), (SPREAD + 9, SPREAD + 10)] {
+            let edge = (field[row + inside] as f64 + field[row + outside] as f64) / 2.0;
+            assert_eq!(edge, 128.0);
+        }
+    }
+
+    #[test]
+    fn values_change_by_64_over_spread_per_pixel() {
+        let (field, width, _) = square();
+        let row = (SPREAD + 5) * width;
+
+        // rising towards the middle of the square
+        for k in 0..5 {
+            let expected = 128.0 + (k as f64 + 0.5) * STEP;
+            assert_eq!(field[row + SPREAD + k] as f64, expected, "{} inside", k);
+        }
+        // and falling away from it, down to 0 at the end of the spread
+        for k in 0..SPREAD {
+            let expected = 128.0 - (k as f64 + 0.5) * STEP;
+            assert_eq!(
+                field[row + SPREAD - 1 - k] as f64,
+                expected,
+                "{} outside",
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn empty_bitmap_is_all_outside() {
+        let (field, width, height) = distance_field(&[0; 6 * 4], 6, 4, 6, SPREAD);
+        assert_eq!((width, height), (6 + 2 * SPREAD, 4 + 2 * SPREAD));
+        assert_eq!(field.len(), width * height);
+        assert!(field.iter().all(|&value| value == 0));
+    }
+}
@@ -0,0 +1,188 @@
+#![allow(dead_code)]
+use std::{collections::HashMap, path::Path};
+
+use freetype as ft;
+use nalgebra_glm as glm;
+
+use super::distance_field::distance_field;
+use super::glyph_atlas::GlyphAtlas;
+
+// width of the glyph atlas, its height grows as glyphs are added
+const ATLAS_WIDTH: u32 = 512;
+// drawn for characters the font does not have
+const FALLBACK_CHARACTERS: [char; 2] = ['\u{FFFD}', '?'];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Character {
+    // position of the glyph bitmap in the glyph atlas, in pixels
+    pub atlas_position: glm::UVec2,
+    pub size: glm::IVec2,
+    pub bearing: glm::IVec2,
+    pub advance: u32,
+    // index of the glyph in the font, used to look up kerning
+    pub glyph_index: u32,
+}
+
+/// A font face rasterized at one pixel size, with its glyphs in their own atlas. The glyphs
+/// are either plain coverage bitmaps or signed distance fields, which can be drawn at any
+/// size with the distance field text shader.
+pub struct Font {
+    face: ft::Face,
+    atlas: GlyphAtlas,
+    characters: HashMap<char, Character>,
+    size: u32,
+    // border in pixels around the glyphs of a distance field font, 0 for bitmap glyphs
+    spread: u32,
+    // distance between the top of a line and the baseline
+    ascent: f32,
+    line_height: f32,
+}
+
+impl Font {
+    pub fn new(library: &ft::Library, file: &Path, size: u32) -> Self {
+        Self::with_spread(library, file, size, 0)
+    }
+
+    /// Creates a font whose glyphs are signed distance fields extending `spread` pixels
+    /// beyond the outline of every glyph
+    pub fn new_distance_field(library: &ft::Library, file: &Path, size: u32, spread: u32) -> Self {
+        Self::with_spread(library, file, size, spread)
+    }
+
+    fn with_spread(library: &ft::Library, file: &Path, size: u32, spread: u32) -> Self {
+        let face = library
+            .new_face(file, 0)
+            .expect("ERROR::FREETYPE: Failed to load font");
+        face.set_pixel_sizes(0, size).unwrap();
+        let line_height = match face.size_metrics() {
+            Some(metrics) => (metrics.height >> 6) as f32,
+            None => size as f32,
+        };
+
+        let mut font = Self {
+            face,
+            atlas: GlyphAtlas::new(ATLAS_WIDTH),
+            characters: HashMap::new(),
+            size,
+            spread,
+            ascent: 0.0,
+            line_height,
+        };
+
+        // the printable ASCII characters are used all the time, so rasterize them up front
+        for c in ' '..='~' {
+            font.character(c);
+        }
+        font.ascent = font.character('H').bearing.y as f32;
+        font
+    }
+
+    /// Looks up a character, rasterizing it into the glyph atlas the first time it is used.
+    /// Characters the font does not have are replaced by a fallback glyph.
+    pub fn character(&mut self, c: char) -> Character {
+        if let Some(character) = self.characters.get(&c) {
+            return *character;
+        }
+
+        let character = match self.rasterize(c) {
+            Some(character) => character,
+            None => FALLBACK_CHARACTERS
+                .iter()
+                .find_map(|&fallback| {
+                    self.characters
+                        .get(&fallback)
+                        .copied()
+                        .or_else(|| self.rasterize(fallback))
+                })
+                .unwrap_or_default(),
+        };
+        // missing characters are stored too, so the font is only asked once
+        self.characters.insert(c, character);
+        character
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<Character> {
+        let glyph_index = self.face.get_char_index(c as usize);
+        if glyph_index == 0 {
+            return None;
+        }
+        if let Err(error) = self.face.load_char(c as usize, ft::face::LoadFlag::RENDER) {
+            eprintln!("ERROR::FREETYPE: Failed to load glyph {:?}: {}", c, error);
+            return None;
+        }
+
+        let glyph = self.face.glyph();
+        let bitmap = glyph.bitmap();
+        let (width, rows, pitch) = (
+            bitmap.width() as usize,
+            bitmap.rows() as usize,
+            bitmap.pitch().unsigned_abs() as usize,
+        );
+
+        // glyphs without pixels, like spaces, have nothing to add a border to
+        let (pixels, width, rows, pitch, border) = if self.spread > 0 && width > 0 && rows > 0 {
+            let spread = self.spread as usize;
+            let (field, width, rows) = distance_field(bitmap.buffer(), width, rows, pitch, spread);
+            (field, width, rows, width, self.spread as i32)
+        } else {
+            (bitmap.buffer().to_vec(), width, rows, pitch, 0)
+        };
+
+        let Some(atlas_position) = self.atlas.add(width as u32, rows as u32, pitch, &pixels) else {
+            eprintln!("ERROR::FREETYPE: Glyph atlas is full, cannot add {:?}", c);
+            return None;
+        };
+
+        Some(Character {
+            atlas_position,
+            size: glm::IVec2::new(width as i32, rows as i32),
+            bearing: glm::IVec2::new(glyph.bitmap_left() - border, glyph.bitmap_top() + border),
+            advance: glyph.advance().x as u32,
+            glyph_index,
+        })
+    }
+
+    /// Extra horizontal space in pixels between two glyphs, negative when they should be
+    /// closer together
+    pub fn kerning(&self, left: u32, right: u32) -> f32 {
+        if !self.face.has_kerning() || left == 0 || right == 0 {
+            return 0.0;
+        }
+        self.face
+            .get_kerning(left, right, ft::face::KerningMode::KerningDefault)
+            .map(|kerning| (kerning.x >> 6) as f32)
+            .unwrap_or(0.0)
+    }
+
+    /// x, y, width and height in texture coordinates of a character in the glyph atlas
+    pub fn uv_rect(&self, character: &Character) -> glm::Vec4 {
+        self.atlas.uv_rect(character.atlas_position, character.size)
+    }
+
+    /// The glyph atlas texture, up to date with every glyph rasterized so far
+    pub fn texture(&mut self) -> u32 {
+        self.atlas.texture()
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn spread(&self) -> u32 {
+        self.spread
+    }
+
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    pub fn delete(&self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.atlas.texture_id);
+        }
+    }
+}
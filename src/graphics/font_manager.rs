@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use freetype as ft;
+
+use super::{asset_error::AssetError, font::Font};
+
+// pixel size distance field fonts are rasterized at before being scaled to any size
+const DISTANCE_FIELD_SIZE: u32 = 48;
+// how far the distance field reaches outside of a glyph, which limits how wide outlines,
+// shadows and glows can be
+const DISTANCE_FIELD_SPREAD: u32 = 8;
+
+/// Keeps track of the font files by name and rasterizes every font at each size it is asked
+/// for, so text is always drawn from glyphs of the right size instead of being stretched.
+pub struct FontManager {
+    library: ft::Library,
+    files: HashMap<String, PathBuf>,
+    fonts: HashMap<(String, u32), Font>,
+    distance_field_fonts: HashMap<String, Font>,
+}
+
+impl FontManager {
+    // initializing FreeType can fail, which `Default` would hide
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            library: ft::Library::init().expect("ERROR::FREETYPE: Could not init FreeType Library"),
+            files: HashMap::new(),
+            fonts: HashMap::new(),
+            distance_field_fonts: HashMap::new(),
+        }
+    }
+
+    /// Registers a font file under a name, it is only rasterized once a size is requested.
+    /// A font that fails to load keeps the name unregistered, so text in it is not drawn.
+    pub fn load_font(&mut self, file: &Path, name: &str) -> Result<(), AssetError> {
+        if let Err(error) = self.library.new_face(file, 0) {
+            return Err(AssetError::Font {
+                path: file.to_path_buf(),
+                error,
+            });
+        }
+        // drop the sizes of a font that was registered under the same name before
+        self.fonts.retain(|(font, _), cached| {
+            if font == name {
+                cached.delete();
+            }
+            font != name
+        });
+        if let Some(font) = self.distance_field_fonts.remove(name) {
+            font.delete();
+        }
+        self.files.insert(name.to_string(), file.to_path_buf());
+        Ok(())
+    }
+
+    /// The font rasterized at `size` pixels, None if no font was loaded under the name
+    pub fn get_font(&mut self, name: &str, size: u32) -> Option<&mut Font> {
+        let library = &self.library;
+        let file = self.files.get(name)?;
+        Some(
+            self.fonts
+                .entry((name.to_string(), size))
+                .or_insert_with(|| Font::new(library, file, size)),
+        )
+    }
+
+    /// The distance field version of a font, one is enough for every size it is drawn at
+    pub fn get_distance_field_font(&mut self, name: &str) -> Option<&mut Font> {
+        let library = &self.library;
+        let file = self.files.get(name)?;
+        Some(
+            self.distance_field_fonts
+                .entry(name.to_string())
+                .or_insert_with(|| {
+                    Font::new_distance_field(
+                        library,
+                        file,
+                        DISTANCE_FIELD_SIZE,
+                        DISTANCE_FIELD_SPREAD,
+                    )
+                }),
+        )
+    }
+
+    pub fn clear(&mut self) {
+        for font in self
+            .fonts
+            .values()
+            .chain(self.distance_field_fonts.values())
+        {
+            font.delete();
+        }
+        self.fonts.clear();
+        self.distance_field_fonts.clear();
+    }
+}
@@ -0,0 +1,208 @@
+#![allow(dead_code)]
+use std::ffi::c_void;
+
+use gl::types::GLuint;
+use nalgebra_glm as glm;
+
+const INITIAL_HEIGHT: u32 = 256;
+const MAX_HEIGHT: u32 = 4096;
+// empty pixels between glyphs, so linear filtering never samples a neighbour
+const PADDING: u32 = 1;
+
+/// Single channel texture that glyphs are added to as they are needed. Glyphs are placed
+/// on shelves from the top down and the texture doubles in height when it runs out of space.
+/// Glyphs are only copied into the pixels on the CPU, the texture is updated by `texture`
+/// right before it is drawn.
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    pub texture_id: GLuint,
+    width: u32,
+    height: u32,
+    // copy of the texture, needed to keep the glyphs when the texture grows
+    pixels: Vec<u8>,
+    // whether glyphs were added since the texture was last uploaded
+    dirty: bool,
+    x: u32,
+    y: u32,
+    shelf_height: u32,
+}
+
+impl GlyphAtlas {
+    pub fn new(width: u32) -> Self {
+        Self {
+            texture_id: 0,
+            width,
+            height: INITIAL_HEIGHT,
+            pixels: vec![0; (width * INITIAL_HEIGHT) as usize],
+            dirty: true,
+            x: 0,
+            y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    /// Removes every glyph, e.g. when a different font is loaded
+    pub fn clear(&mut self) {
+        self.height = INITIAL_HEIGHT;
+        self.pixels = vec![0; (self.width * self.height) as usize];
+        self.x = 0;
+        self.y = 0;
+        self.shelf_height = 0;
+        self.dirty = true;
+    }
+
+    /// Copies a glyph bitmap with rows `pitch` bytes apart into the atlas and returns its
+    /// position in pixels, or `None` when the atlas is full. Positions stay valid when the
+    /// atlas grows, texture coordinates do not, so these are only turned into texture
+    /// coordinates with `uv_rect` once every glyph of a text has been added.
+    pub fn add(
+        &mut self,
+        width: u32,
+        rows: u32,
+        pitch: usize,
+        buffer: &[u8],
+    ) -> Option<glm::UVec2> {
+        if width + 2 * PADDING > self.width {
+            return None;
+        }
+
+        if self.x + width + 2 * PADDING > self.width {
+            self.x = 0;
+            self.y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        while self.y + rows + 2 * PADDING > self.height {
+            if self.height * 2 > MAX_HEIGHT {
+                return None;
+            }
+            self.height *= 2;
+            self.pixels.resize((self.width * self.height) as usize, 0);
+        }
+
+        let x = self.x + PADDING;
+        let y = self.y + PADDING;
+        for row in 0..rows as usize {
+            let source = &buffer[row * pitch..row * pitch + width as usize];
+            let start = (y as usize + row) * self.width as usize + x as usize;
+            self.pixels[start..start + width as usize].copy_from_slice(source);
+        }
+
+        self.dirty = true;
+        self.x += width + 2 * PADDING;
+        self.shelf_height = self.shelf_height.max(rows + 2 * PADDING);
+
+        Some(glm::UVec2::new(x, y))
+    }
+
+    /// x, y, width and height in texture coordinates of the given rectangle in pixels
+    pub fn uv_rect(&self, position: glm::UVec2, size: glm::IVec2) -> glm::Vec4 {
+        glm::vec4(
+            position.x as f32 / self.width as f32,
+            position.y as f32 / self.height as f32,
+            size.x as f32 / self.width as f32,
+            size.y as f32 / self.height as f32,
+        )
+    }
+
+    /// The atlas texture, uploading the glyphs added since it was last drawn
+    pub fn texture(&mut self) -> GLuint {
+        if self.dirty {
+            self.upload();
+            self.dirty = false;
+        }
+        self.texture_id
+    }
+
+    fn upload(&mut self) {
+        unsafe {
+            if self.texture_id == 0 {
+                gl::GenTextures(1, &mut self.texture_id);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RED as i32,
+                self.width as i32,
+                self.height as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                self.pixels.as_ptr() as *const c_void,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(width: u32, rows: u32, value: u8) -> Vec<u8> {
+        vec![value; (width * rows) as usize]
+    }
+
+    #[test]
+    fn places_glyphs_on_shelves_with_padding() {
+        let mut atlas = GlyphAtlas::new(64);
+        let first = atlas.add(20, 10, 20, &glyph(20, 10, 1)).unwrap();
+        let second = atlas.add(20, 14, 20, &glyph(20, 14, 2)).unwrap();
+        // the third one no longer fits next to the others and starts a new shelf below them
+        let third = atlas.add(30, 4, 30, &glyph(30, 4, 3)).unwrap();
+
+        assert_eq!(first, glm::UVec2::new(1, 1));
+        assert_eq!(second, glm::UVec2::new(23, 1));
+        assert_eq!(third, glm::UVec2::new(1, 17));
+        assert_eq!(atlas.pixels[(17 * 64 + 1) as usize], 3);
+        // the padding around a glyph stays empty
+        assert_eq!(atlas.pixels[0], 0);
+        assert_eq!(atlas.pixels[(11 * 64 + 1) as usize], 0);
+    }
+
+    #[test]
+    fn growing_mid_string_keeps_glyph_positions_but_moves_texture_coordinates() {
+        let mut atlas = GlyphAtlas::new(64);
+        let first = atlas.add(8, 8, 8, &glyph(8, 8, 7)).unwrap();
+        let before = atlas.uv_rect(first, glm::IVec2::new(8, 8));
+
+        // a glyph taller than the space left doubles the height of the atlas
+        let tall = atlas.add(8, 300, 8, &glyph(8, 300, 9)).unwrap();
+        assert_eq!(atlas.height, 512);
+        assert_eq!(tall, glm::UVec2::new(11, 1));
+
+        // the first glyph is still where it was, but its texture coordinates have changed,
+        // which is why they are only worked out after every glyph of a text is added
+        assert_eq!(atlas.pixels[(first.y * 64 + first.x) as usize], 7);
+        let after = atlas.uv_rect(first, glm::IVec2::new(8, 8));
+        assert_eq!(after.x, before.x);
+        assert_eq!(after.y * 2.0, before.y);
+        assert_eq!(after.w * 2.0, before.w);
+        assert_eq!(after.y * atlas.height as f32, first.y as f32);
+    }
+
+    #[test]
+    fn rejects_glyphs_that_can_never_fit() {
+        let mut atlas = GlyphAtlas::new(64);
+        assert_eq!(atlas.add(63, 4, 63, &glyph(63, 4, 1)), None);
+        assert_eq!(atlas.add(4, MAX_HEIGHT, 4, &glyph(4, MAX_HEIGHT, 1)), None);
+        assert!(atlas.add(4, 4, 4, &glyph(4, 4, 1)).is_some());
+    }
+
+    #[test]
+    fn clear_starts_over_at_the_initial_height() {
+        let mut atlas = GlyphAtlas::new(64);
+        atlas.add(8, 300, 8, &glyph(8, 300, 1)).unwrap();
+        atlas.clear();
+        assert_eq!(atlas.height, INITIAL_HEIGHT);
+        assert_eq!(
+            atlas.add(8, 8, 8, &glyph(8, 8, 1)),
+            Some(glm::UVec2::new(1, 1))
+        );
+    }
+}
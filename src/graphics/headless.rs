@@ -0,0 +1,258 @@
+#![allow(dead_code)]
+use image::RgbaImage;
+
+use super::screenshot::read_framebuffer;
+
+/// OpenGL context for rendering without showing anything, e.g. in tests. It renders into a
+/// framebuffer of its own, so the frame does not depend on a window being mapped or on its
+/// size.
+///
+/// On Linux the context comes from EGL without any surface, which works without a display
+/// and with Mesa's software renderer on machines without a GPU. Elsewhere it makes a hidden
+/// window current, which needs a desktop session.
+pub struct HeadlessContext {
+    pub width: u32,
+    pub height: u32,
+    fbo: u32,
+    rbo: u32,
+    // dropped after the framebuffer is deleted, which still needs the context
+    _context: platform::Context,
+}
+
+impl HeadlessContext {
+    pub fn new(width: u32, height: u32) -> Result<Self, String> {
+        let context = platform::Context::new(width, height)?;
+
+        let mut fbo = 0;
+        let mut rbo = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenRenderbuffers(1, &mut rbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width as i32, height as i32);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                rbo,
+            );
+            let complete = gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if !complete {
+                return Err("Failed to initialize offscreen FBO".to_string());
+            }
+
+            // same configuration as the window of the game
+            gl::Viewport(0, 0, width as i32, height as i32);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            fbo,
+            rbo,
+            _context: context,
+        })
+    }
+
+    /// Framebuffer to render the frame into instead of the window
+    pub fn framebuffer(&self) -> u32 {
+        self.fbo
+    }
+
+    /// Clears the frame to black, like the game loop does before every frame
+    pub fn clear(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+    }
+
+    /// The rendered frame, waiting for rendering to finish
+    pub fn read_pixels(&self) -> RgbaImage {
+        unsafe {
+            gl::Finish();
+        }
+        read_framebuffer(self.fbo, self.width, self.height)
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteRenderbuffers(1, &self.rbo);
+        }
+    }
+}
+
+/// OpenGL 3.3 core context made current without a surface through Mesa's surfaceless EGL
+/// platform
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{
+        ffi::{c_char, c_void, CString},
+        ptr,
+    };
+
+    type EGLDisplay = *mut c_void;
+    type EGLContext = *mut c_void;
+
+    const EGL_NONE: i32 = 0x3038;
+    const EGL_OPENGL_API: u32 = 0x30A2;
+    const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+    const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+    const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+    const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+    const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x1;
+
+    #[link(name = "EGL")]
+    extern "C" {
+        fn eglGetPlatformDisplay(
+            platform: u32,
+            native_display: *mut c_void,
+            attributes: *const isize,
+        ) -> EGLDisplay;
+        fn eglInitialize(display: EGLDisplay, major: *mut i32, minor: *mut i32) -> u32;
+        fn eglTerminate(display: EGLDisplay) -> u32;
+        fn eglBindAPI(api: u32) -> u32;
+        fn eglCreateContext(
+            display: EGLDisplay,
+            config: *mut c_void,
+            share_context: EGLContext,
+            attributes: *const i32,
+        ) -> EGLContext;
+        fn eglDestroyContext(display: EGLDisplay, context: EGLContext) -> u32;
+        fn eglMakeCurrent(
+            display: EGLDisplay,
+            draw: *mut c_void,
+            read: *mut c_void,
+            context: EGLContext,
+        ) -> u32;
+        fn eglGetProcAddress(name: *const c_char) -> *const c_void;
+        fn eglGetError() -> i32;
+    }
+
+    pub struct Context {
+        display: EGLDisplay,
+        context: EGLContext,
+    }
+
+    impl Context {
+        pub fn new(_width: u32, _height: u32) -> Result<Self, String> {
+            unsafe {
+                let display = eglGetPlatformDisplay(
+                    EGL_PLATFORM_SURFACELESS_MESA,
+                    ptr::null_mut(),
+                    ptr::null(),
+                );
+                if display.is_null()
+                    || eglInitialize(display, ptr::null_mut(), ptr::null_mut()) == 0
+                {
+                    return Err(format!(
+                        "Failed to initialize surfaceless EGL display: {:#x}",
+                        eglGetError()
+                    ));
+                }
+                if eglBindAPI(EGL_OPENGL_API) == 0 {
+                    eglTerminate(display);
+                    return Err(format!("Failed to bind OpenGL API: {:#x}", eglGetError()));
+                }
+
+                let attributes = [
+                    EGL_CONTEXT_MAJOR_VERSION,
+                    3,
+                    EGL_CONTEXT_MINOR_VERSION,
+                    3,
+                    EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                    EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                    EGL_NONE,
+                ];
+                // without a surface there is nothing a config would describe
+                let context = eglCreateContext(
+                    display,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    attributes.as_ptr(),
+                );
+                if context.is_null() {
+                    let error = eglGetError();
+                    eglTerminate(display);
+                    return Err(format!("Failed to create EGL context: {:#x}", error));
+                }
+                if eglMakeCurrent(display, ptr::null_mut(), ptr::null_mut(), context) == 0 {
+                    let error = eglGetError();
+                    eglDestroyContext(display, context);
+                    eglTerminate(display);
+                    return Err(format!("Failed to make EGL context current: {:#x}", error));
+                }
+
+                gl::load_with(|symbol| {
+                    let symbol = CString::new(symbol).unwrap();
+                    eglGetProcAddress(symbol.as_ptr())
+                });
+                Ok(Self { display, context })
+            }
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            unsafe {
+                eglMakeCurrent(
+                    self.display,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+                eglDestroyContext(self.display, self.context);
+                eglTerminate(self.display);
+            }
+        }
+    }
+}
+
+/// OpenGL 3.3 core context of a hidden window
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::sync::mpsc::Receiver;
+
+    use glfw::{Context as _, Glfw, Window, WindowEvent};
+
+    pub struct Context {
+        window: Window,
+        // events of the hidden window, kept so glfw has somewhere to send them
+        _events: Receiver<(f64, WindowEvent)>,
+        _glfw: Glfw,
+    }
+
+    impl Context {
+        pub fn new(width: u32, height: u32) -> Result<Self, String> {
+            let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS)
+                .map_err(|error| format!("Failed to initialize GLFW: {:?}", error))?;
+            glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
+            glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+                glfw::OpenGlProfileHint::Core,
+            ));
+            #[cfg(target_os = "macos")]
+            glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+            glfw.window_hint(glfw::WindowHint::Visible(false));
+
+            let (mut window, events) = glfw
+                .create_window(width, height, "Breakout", glfw::WindowMode::Windowed)
+                .ok_or("Failed to create hidden GLFW window")?;
+            window.make_current();
+            gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+
+            Ok(Self {
+                window,
+                _events: events,
+                _glfw: glfw,
+            })
+        }
+    }
+}
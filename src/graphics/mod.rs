@@ -1,18 +1,33 @@
 #![allow(dead_code)]
+pub mod asset_error;
+pub mod bloom;
+pub mod camera;
+pub mod distance_field;
+pub mod font;
+pub mod font_manager;
+pub mod glyph_atlas;
+pub mod headless;
+pub mod post_effect;
 pub mod post_processor;
+pub mod recorder;
+pub mod screenshot;
 pub mod shader;
 pub mod shader_manager;
-pub mod sprite_renderer;
+pub mod shader_preprocessor;
+pub mod sprite_batch;
 pub mod text_renderer;
 pub mod texture;
+pub mod texture_atlas;
 pub mod texture_manager;
 
-use std::path::Path;
+use std::{path::Path, rc::Rc};
 
 use nalgebra_glm as glm;
 
+use asset_error::AssetReport;
+use camera::Camera;
 use shader_manager::ShaderManager;
-use sprite_renderer::SpriteRenderer;
+use sprite_batch::SpriteBatch;
 use texture_manager::TextureManager;
 
 #[derive(Debug)]
@@ -21,7 +36,10 @@ pub struct Graphics {
     pub height: u32,
     pub shader_manager: ShaderManager,
     pub texture_manager: TextureManager,
-    pub sprite_renderer: SpriteRenderer,
+    pub sprite_batch: SpriteBatch,
+    pub camera: Camera,
+    // every texture and shader that failed to load
+    pub asset_report: AssetReport,
 }
 
 impl Graphics {
@@ -31,49 +49,62 @@ impl Graphics {
         mut shader_manager: ShaderManager,
         mut texture_manager: TextureManager,
     ) -> Self {
-        let shader = shader_manager.load_shader(
-            Path::new("shaders/sprite.vs"),
-            Path::new("shaders/sprite.frag"),
-            None,
-            "sprite".to_string(),
-        );
-
-        let projection = glm::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        let mut asset_report = AssetReport::new();
+        let camera = Camera::new(width, height);
+        let projection = camera.projection();
 
-        shader_manager
-            .get_shader("sprite")
-            .use_program()
-            .set_int("image\0", 0);
+        let batch_shader = asset_report
+            .record(shader_manager.load_shader(
+                Path::new("shaders/sprite_batch.vs"),
+                Path::new("shaders/sprite_batch.frag"),
+                None,
+                "sprite_batch".to_string(),
+            ))
+            .unwrap_or_else(|| Rc::new(shader_manager.get_shader("sprite_batch").clone()));
 
-        shader_manager
-            .get_shader("sprite")
-            .set_mat4("projection\0", &projection);
+        batch_shader.use_program().set_int("image", 0);
+        batch_shader.set_mat4("projection", &projection);
+        batch_shader.set_mat4("view", &camera.view());
 
-        texture_manager.load_texture(
+        asset_report.record(texture_manager.load_texture(
             Path::new("resources/textures/awesomeface.png"),
             true,
             "face",
-        );
+        ));
 
         Self {
             width,
             height,
             shader_manager,
             texture_manager,
-            sprite_renderer: SpriteRenderer::new(shader),
+            sprite_batch: SpriteBatch::new(batch_shader),
+            camera,
+            asset_report,
         }
     }
 
     pub fn render(&mut self) {
-        self.sprite_renderer.draw_sprite(
+        self.set_view(&self.camera.view());
+        self.sprite_batch.draw_sprite(
             self.texture_manager.get_texture("background"),
             glm::vec2(0.0, 0.0),
             glm::vec2(self.width as f32, self.height as f32),
             0.0,
             glm::vec3(1.0, 1.0, 1.0),
+            sprite_batch::layer::BACKGROUND,
         );
     }
 
+    /// Sets the view matrix of the sprite and particle shaders, the camera's view for the
+    /// world or the identity for anything fixed on the screen
+    pub fn set_view(&self, view: &glm::Mat4) {
+        for name in ["sprite_batch", "particle"] {
+            if let Ok(shader) = self.shader_manager.try_get_shader(name) {
+                shader.use_program().set_mat4("view", view);
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
         self.texture_manager.clear();
         self.shader_manager.clear();
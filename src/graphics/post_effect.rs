@@ -0,0 +1,147 @@
+#![allow(dead_code)]
+use nalgebra_glm as glm;
+
+use super::{post_processor::KERNEL_SIZE, shader::Shader, shader_manager::ShaderManager};
+
+/// One pass of the post processing chain. It draws the output of the pass before it through
+/// its own shader, blended with its input by `intensity`, so it can be faded in and out.
+///
+/// Every effect shader gets the `scene` texture, `time` in seconds, its `intensity` and the
+/// size of one pixel in texture coordinates as `texelSize`, other parameters are set with
+/// `parameters`.
+#[derive(Debug)]
+pub struct PostEffect {
+    pub name: String,
+    shader: Shader,
+    intensity: f32,
+    target_intensity: f32,
+    // change of the intensity per second while fading
+    fade_speed: f32,
+}
+
+impl PostEffect {
+    pub fn new(name: &str, shader: Shader) -> Self {
+        Self {
+            name: name.to_string(),
+            shader,
+            intensity: 0.0,
+            target_intensity: 0.0,
+            fade_speed: 0.0,
+        }
+    }
+
+    /// The shader of the effect, in use so its parameters can be set right away
+    pub fn parameters(&self) -> &Shader {
+        self.shader.use_program()
+    }
+
+    pub(super) fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Whether the effect is on or fading in, an effect fading out is not enabled anymore
+    /// but still drawn until it has faded out completely
+    pub fn is_enabled(&self) -> bool {
+        self.target_intensity > 0.0
+    }
+
+    /// Whether the effect is drawn at all
+    pub fn is_active(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    /// Turns the effect on or off immediately
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.set_intensity(if enabled { 1.0 } else { 0.0 });
+    }
+
+    /// Sets the intensity immediately, stopping any fade
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0.0, 1.0);
+        self.target_intensity = self.intensity;
+    }
+
+    /// Changes the intensity to `target` over `duration` seconds
+    pub fn fade_to(&mut self, target: f32, duration: f32) {
+        self.target_intensity = target.clamp(0.0, 1.0);
+        if duration <= 0.0 {
+            self.intensity = self.target_intensity;
+            return;
+        }
+        self.fade_speed = (self.target_intensity - self.intensity).abs() / duration;
+    }
+
+    pub fn fade_in(&mut self, duration: f32) {
+        self.fade_to(1.0, duration);
+    }
+
+    pub fn fade_out(&mut self, duration: f32) {
+        self.fade_to(0.0, duration);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let step = self.fade_speed * dt;
+        if (self.target_intensity - self.intensity).abs() <= step {
+            self.intensity = self.target_intensity;
+        } else if self.target_intensity > self.intensity {
+            self.intensity += step;
+        } else {
+            self.intensity -= step;
+        }
+    }
+}
+
+/// The effects of the original game, in the order they are applied: chaos swirls the scene
+/// around and only shows its edges, confuse turns it upside down and inverts its colors and
+/// shake blurs it while the camera shakes.
+pub fn builtin_effects(shader_manager: &ShaderManager) -> Vec<PostEffect> {
+    // distance between the samples of the kernels in pixels, so they stay the same at any
+    // size of the window
+    let offset = 2.0;
+
+    #[rustfmt::skip]
+    let offsets: [[f32; 2]; KERNEL_SIZE] = [
+        [ -offset,  offset ],
+        [  0.0,     offset ],
+        [  offset,  offset ],
+        [ -offset,  0.0    ],
+        [  0.0,     0.0    ],
+        [  offset,  0.0    ],
+        [ -offset, -offset ],
+        [  0.0,    -offset ],
+        [  offset, -offset ],
+    ];
+
+    #[rustfmt::skip]
+    let edge_kernel: [f32; KERNEL_SIZE] = [
+        -1.0, -1.0, -1.0,
+        -1.0,  8.0, -1.0,
+        -1.0, -1.0, -1.0
+    ];
+
+    #[rustfmt::skip]
+    let blur_kernel: [f32; KERNEL_SIZE] = [
+        1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
+        2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0,
+        1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0
+    ];
+
+    let offsets = offsets.map(glm::Vec2::from);
+
+    let chaos = PostEffect::new("chaos", shader_manager.get_shader("chaos").clone());
+    chaos.parameters().set_vec2_array("offsets", &offsets);
+    chaos.parameters().set_float_array("kernel", &edge_kernel);
+    chaos.parameters().set_float("strength", 0.3);
+
+    let confuse = PostEffect::new("confuse", shader_manager.get_shader("confuse").clone());
+
+    let shake = PostEffect::new("shake", shader_manager.get_shader("shake").clone());
+    shake.parameters().set_vec2_array("offsets", &offsets);
+    shake.parameters().set_float_array("kernel", &blur_kernel);
+
+    vec![chaos, confuse, shake]
+}
@@ -1,66 +1,83 @@
 #![allow(dead_code)]
-use std::ffi::{c_void, CStr};
+use std::ffi::c_void;
 
-use gl::types::{GLsizei, GLsizeiptr};
+use gl::types::{GLenum, GLsizei, GLsizeiptr};
+use image::RgbaImage;
+use nalgebra_glm as glm;
 
-use super::{shader::Shader, texture::Texture2D};
+use super::{
+    bloom::Bloom, post_effect::PostEffect, screenshot::read_framebuffer, texture::Texture2D,
+};
 
+/// Number of samples of the convolution kernels, shared with the shader as a define
+pub const KERNEL_SIZE: usize = 9;
+
+/// Framebuffer with a texture to render an effect pass into
+#[derive(Debug)]
+pub(super) struct RenderTarget {
+    pub fbo: u32,
+    pub texture: Texture2D,
+}
+
+impl RenderTarget {
+    /// `wrap` is how the texture is sampled outside of it, effects like chaos that move the
+    /// scene around need it to repeat
+    pub fn new(width: i32, height: i32, wrap: GLenum) -> Self {
+        let mut fbo = 0;
+        let mut texture = Texture2D::new();
+        texture.wrap_s = wrap;
+        texture.wrap_t = wrap;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            texture.generate(width, height, &[]);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture.id,
+                0,
+            );
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                eprintln!("ERROR::POSTPROCESSOR: Failed to initialize FBO");
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        Self { fbo, texture }
+    }
+}
+
+/// Renders the scene into a multisampled framebuffer and then draws it to the screen
+/// through bloom and a chain of effects. Every active pass reads the output of the one
+/// before it, switching between two render targets, and the last one draws to the screen,
+/// or to the framebuffer set with `set_output`.
 #[derive(Debug)]
 pub struct PostProcessor {
-    post_processing_shader: Shader,
-    texture: Texture2D,
     width: i32,
     height: i32,
-    pub chaos: bool,
-    pub confuse: bool,
-    pub shake: bool,
-    msfbo: u32, // MSFBO = Multisampled FBO. FBO is regular, used for blitting MS color-buffer to texture.
-    fbo: u32,
-    rbo: u32, // RBO is used for multisampled color buffer.
+    effects: Vec<PostEffect>,
+    /// Applied to the scene before any of the effects
+    pub bloom: Option<Bloom>,
+    msfbo: u32, // MSFBO = Multisampled FBO. The scene is resolved into `scene`, used for blitting MS color-buffer to texture.
+    rbo: u32,   // RBO is used for multisampled color buffer.
+    scene: RenderTarget,
+    ping_pong: [RenderTarget; 2],
+    // framebuffer the final frame is drawn to, 0 being the window
+    output: u32,
     vao: u32,
 }
 
 impl PostProcessor {
-    pub fn new(shader: Shader, width: i32, height: i32) -> Self {
+    pub fn new(width: i32, height: i32) -> Self {
         let mut msfbo = 0;
-        let mut fbo = 0;
         let mut rbo = 0;
-        let mut texture = Texture2D::new();
         let mut vao = 0;
 
-        let offset = 1.0 / 300.0;
-
-        #[rustfmt::skip]
-        let offsets: [[f32; 2]; 9] = [
-            [ -offset,  offset ],
-            [  0.0,     offset ],
-            [  offset,  offset ],
-            [ -offset,  0.0    ],
-            [  0.0,     0.0    ],
-            [  offset,  0.0    ],
-            [ -offset, -offset ],
-            [  0.0,    -offset ],
-            [  offset, -offset ],
-        ];
-
-        #[rustfmt::skip]
-        let edge_kernel: [i32; 9] = [
-            -1, -1, -1,
-            -1,  8, -1,
-            -1, -1, -1
-        ];
-
-        #[rustfmt::skip]
-        let blur_kernel: [f32; 9] = [
-            1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
-            2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0,
-            1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0
-        ];
-
         // initialize renderbuffer/framebuffer object
         unsafe {
             gl::GenFramebuffers(1, &mut msfbo);
-            gl::GenFramebuffers(1, &mut fbo);
             gl::GenRenderbuffers(1, &mut rbo);
 
             // initialize renderbuffer storage with multisampled color buffer (don't need depth/stencil buffer)
@@ -82,72 +99,87 @@ impl PostProcessor {
                 eprintln!("ERROR::POSTPROCESSOR: Failed to initialize MSFBO");
             }
 
-            // also intialize the FBO/texture to blit multisampled color-buffer to; used for shader operations(for postprocessing effects)
-            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
-            texture.generate(width, height, &[]);
-            gl::FramebufferTexture2D(
-                gl::FRAMEBUFFER,
-                gl::COLOR_ATTACHMENT0,
-                gl::TEXTURE_2D,
-                texture.id,
-                0,
-            );
-
-            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                eprintln!("ERROR::POSTPROCESSOR: Failed to initialize FBO");
-            }
-
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
 
-            // initialize render data and uniforms
+            // initialize render data
             Self::init_render_data(&mut vao);
-
-            shader.set_int("scene\0", 0);
-            shader.use_program();
-
-            gl::Uniform2fv(
-                gl::GetUniformLocation(
-                    shader.id,
-                    CStr::from_bytes_with_nul_unchecked("offsets\0".as_bytes()).as_ptr(),
-                ),
-                9,
-                offsets.as_ptr() as *const f32,
-            );
-
-            gl::Uniform1iv(
-                gl::GetUniformLocation(
-                    shader.id,
-                    CStr::from_bytes_with_nul_unchecked("edge_kernel\0".as_bytes()).as_ptr(),
-                ),
-                9,
-                edge_kernel.as_ptr(),
-            );
-
-            gl::Uniform1fv(
-                gl::GetUniformLocation(
-                    shader.id,
-                    CStr::from_bytes_with_nul_unchecked("blur_kernel\0".as_bytes()).as_ptr(),
-                ),
-                9,
-                blur_kernel.as_ptr(),
-            );
         }
 
         Self {
-            post_processing_shader: shader,
-            texture,
             width,
             height,
-            chaos: false,
-            confuse: false,
-            shake: false,
+            effects: vec![],
+            bloom: None,
             msfbo,
-            fbo,
             rbo,
+            // also intialize the targets to blit the multisampled color-buffer to and to run
+            // the effects on; used for shader operations(for postprocessing effects)
+            scene: RenderTarget::new(width, height, gl::REPEAT),
+            ping_pong: [
+                RenderTarget::new(width, height, gl::REPEAT),
+                RenderTarget::new(width, height, gl::REPEAT),
+            ],
+            output: 0,
             vao,
         }
     }
 
+    /// Adds an effect at the end of the chain, it starts out disabled
+    pub fn add_effect(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    pub fn effect(&self, name: &str) -> Option<&PostEffect> {
+        self.effects.iter().find(|effect| effect.name == name)
+    }
+
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.effect(name).is_some_and(|effect| effect.is_enabled())
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        match self.effect_mut(name) {
+            Some(effect) => effect.set_enabled(enabled),
+            None => eprintln!("ERROR::POSTPROCESSOR: No effect named {}", name),
+        }
+    }
+
+    /// Fades an effect in or out over `duration` seconds
+    pub fn fade(&mut self, name: &str, enabled: bool, duration: f32) {
+        match self.effect_mut(name) {
+            Some(effect) if enabled => effect.fade_in(duration),
+            Some(effect) => effect.fade_out(duration),
+            None => eprintln!("ERROR::POSTPROCESSOR: No effect named {}", name),
+        }
+    }
+
+    /// Draws the final frame to `fbo` instead of the window, e.g. to render offscreen
+    pub fn set_output(&mut self, fbo: u32) {
+        self.output = fbo;
+    }
+
+    /// Framebuffer the final frame is drawn to, 0 being the window
+    pub fn output(&self) -> u32 {
+        self.output
+    }
+
+    /// The scene as it was rendered, before bloom and the effects. Only valid after
+    /// `end_render`.
+    pub fn capture_scene(&self) -> RgbaImage {
+        read_framebuffer(self.scene.fbo, self.width as u32, self.height as u32)
+    }
+
+    /// Advances the fades of the effects
+    pub fn update(&mut self, dt: f32) {
+        for effect in &mut self.effects {
+            effect.update(dt);
+        }
+    }
+
     // prepares the postprocessor's framebuffer operations before rendering the game
     pub fn begin_render(&self) {
         unsafe {
@@ -161,7 +193,7 @@ impl PostProcessor {
         // now resolve multisampled color-buffer into intermediate FBO to store to texture
         unsafe {
             gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.msfbo);
-            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.scene.fbo);
             gl::BlitFramebuffer(
                 0,
                 0,
@@ -179,26 +211,86 @@ impl PostProcessor {
         }
     }
 
+    /// Draws the resolved scene to the screen through bloom and every active effect
     pub fn render(&self, time: f32) {
-        // set uniforms/options
-        self.post_processing_shader.use_program();
+        let bloom = self.bloom.as_ref().filter(|bloom| bloom.is_active());
+        let active: Vec<&PostEffect> = self.effects.iter().filter(|e| e.is_active()).collect();
+        let passes = active.len() + bloom.is_some() as usize;
+
         unsafe {
-            self.post_processing_shader.set_float("time\0", time);
-            // TODO: Should this be set_int instead?
-            self.post_processing_shader
-                .set_bool("confuse\0", self.confuse);
-            self.post_processing_shader.set_bool("chaos\0", self.chaos);
-            self.post_processing_shader.set_bool("shake\0", self.shake);
-
-            // render texture quad
-            gl::ActiveTexture(gl::TEXTURE0);
-            self.texture.bind();
-            gl::BindVertexArray(self.vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, 6);
-            gl::BindVertexArray(0);
+            if passes == 0 {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.scene.fbo);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.output);
+                gl::BlitFramebuffer(
+                    0,
+                    0,
+                    self.width,
+                    self.height,
+                    0,
+                    0,
+                    self.width,
+                    self.height,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.output);
+                return;
+            }
+
+            let mut input = &self.scene.texture;
+            let mut pass = 0;
+
+            if let Some(bloom) = bloom {
+                let glow = bloom.glow(input, self.vao);
+                let target = self.bind_target(pass, passes);
+                bloom.composite_shader();
+                gl::ActiveTexture(gl::TEXTURE1);
+                glow.bind();
+                gl::ActiveTexture(gl::TEXTURE0);
+                input.bind();
+                draw_quad(self.vao);
+
+                input = &target.texture;
+                pass += 1;
+            }
+
+            let texel_size = glm::vec2(1.0 / self.width as f32, 1.0 / self.height as f32);
+            for effect in active {
+                let target = self.bind_target(pass, passes);
+
+                // set uniforms/options
+                let shader = effect.shader().use_program();
+                shader.set_int("scene", 0);
+                shader.set_float("intensity", effect.intensity());
+                if shader.has_uniform("time") {
+                    shader.set_float("time", time);
+                }
+                if shader.has_uniform("texelSize") {
+                    shader.set_vec2("texelSize", &texel_size);
+                }
+
+                // render texture quad
+                gl::ActiveTexture(gl::TEXTURE0);
+                input.bind();
+                draw_quad(self.vao);
+
+                input = &target.texture;
+                pass += 1;
+            }
+            // anything drawn after post processing, like the text, goes on top of the frame
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.output);
         }
     }
 
+    /// Binds the framebuffer a pass draws into, which is the output for the last pass, and
+    /// returns the target whose texture the next pass reads from
+    unsafe fn bind_target(&self, pass: usize, passes: usize) -> &RenderTarget {
+        let target = &self.ping_pong[pass % 2];
+        let last = pass + 1 == passes;
+        gl::BindFramebuffer(gl::FRAMEBUFFER, if last { self.output } else { target.fbo });
+        target
+    }
+
     fn init_render_data(vao: &mut u32) {
         let mut vbo = 0;
 
@@ -243,3 +335,10 @@ impl PostProcessor {
         }
     }
 }
+
+/// Draws a quad covering the whole framebuffer
+pub(super) unsafe fn draw_quad(vao: u32) {
+    gl::BindVertexArray(vao);
+    gl::DrawArrays(gl::TRIANGLES, 0, 6);
+    gl::BindVertexArray(0);
+}
@@ -0,0 +1,206 @@
+#![allow(dead_code)]
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::mpsc::{self, SyncSender},
+    thread::{self, JoinHandle},
+};
+
+use gl::types::{GLsizeiptr, GLuint};
+use image::RgbaImage;
+
+use super::screenshot::opaque;
+
+// frames are read back this many frames after they were rendered, by then the GPU has
+// finished copying them and mapping the buffer does not wait for it
+const PIXEL_BUFFERS: usize = 3;
+// frames waiting to be written at most. When the writer falls behind the game waits for it
+// instead of piling up frames in memory, the fixed time step keeps the recording smooth.
+const QUEUED_FRAMES: usize = 8;
+
+/// Where the frames of a recording go
+#[derive(Debug, Clone)]
+pub enum RecordingOutput {
+    /// Numbered PNG files in a directory
+    Frames(PathBuf),
+    /// Raw RGBA frames written to the standard input of a command, e.g. an ffmpeg process
+    Encoder(Vec<String>),
+}
+
+/// Records every rendered frame of the window. Frames are copied into pixel buffers on the
+/// GPU and only read a few frames later, so the game does not wait for the copy, and they
+/// are written out on a thread of their own.
+#[derive(Debug)]
+pub struct Recorder {
+    width: u32,
+    height: u32,
+    pixel_buffers: [GLuint; PIXEL_BUFFERS],
+    // pixel buffers holding frames that were not read yet, oldest first
+    pending: VecDeque<usize>,
+    frames: u64,
+    sender: Option<SyncSender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    pub fn start(width: u32, height: u32, output: RecordingOutput) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(QUEUED_FRAMES);
+        let writer = match output {
+            RecordingOutput::Frames(directory) => {
+                fs::create_dir_all(&directory)?;
+                thread::spawn(move || {
+                    for (number, pixels) in receiver.into_iter().enumerate() {
+                        let file = directory.join(format!("frame_{:06}.png", number));
+                        let image =
+                            RgbaImage::from_raw(width, height, flip_rows(pixels, width)).unwrap();
+                        if let Err(error) = image.save(&file) {
+                            eprintln!(
+                                "ERROR::RECORDER: Failed to save {}: {}",
+                                file.display(),
+                                error
+                            );
+                        }
+                    }
+                })
+            }
+            RecordingOutput::Encoder(command) => {
+                let Some((program, arguments)) = command.split_first() else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the encoder command is empty",
+                    ));
+                };
+                let mut encoder = Command::new(program)
+                    .args(arguments)
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                let mut stdin = encoder.stdin.take().unwrap();
+                thread::spawn(move || {
+                    for pixels in receiver {
+                        if let Err(error) = stdin.write_all(&flip_rows(pixels, width)) {
+                            eprintln!("ERROR::RECORDER: Failed to write to encoder: {}", error);
+                            break;
+                        }
+                    }
+                    // closing its input tells the encoder the recording is over
+                    drop(stdin);
+                    if let Err(error) = encoder.wait() {
+                        eprintln!("ERROR::RECORDER: Encoder did not finish: {}", error);
+                    }
+                })
+            }
+        };
+
+        let mut pixel_buffers = [0; PIXEL_BUFFERS];
+        unsafe {
+            gl::GenBuffers(PIXEL_BUFFERS as i32, pixel_buffers.as_mut_ptr());
+            for buffer in pixel_buffers {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                gl::BufferData(
+                    gl::PIXEL_PACK_BUFFER,
+                    (width * height * 4) as GLsizeiptr,
+                    std::ptr::null(),
+                    gl::STREAM_READ,
+                );
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixel_buffers,
+            pending: VecDeque::new(),
+            frames: 0,
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    /// Number of frames recorded so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Starts copying the frame that was just rendered to `fbo`, 0 being the window, call
+    /// before swapping buffers
+    pub fn capture(&mut self, fbo: u32) {
+        if self.pending.len() == PIXEL_BUFFERS {
+            self.read_oldest();
+        }
+        let buffer = (self.frames % PIXEL_BUFFERS as u64) as usize;
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pixel_buffers[buffer]);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            // with a pack buffer bound this only starts the copy and returns right away
+            gl::ReadPixels(
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            );
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        self.pending.push_back(buffer);
+        self.frames += 1;
+    }
+
+    /// Writes out the frames that are still on the GPU and waits until every frame is saved
+    /// or sent to the encoder
+    pub fn stop(mut self) {
+        while !self.pending.is_empty() {
+            self.read_oldest();
+        }
+        unsafe {
+            gl::DeleteBuffers(PIXEL_BUFFERS as i32, self.pixel_buffers.as_ptr());
+        }
+        // the writer stops once the channel is closed and everything in it is written
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                eprintln!("ERROR::RECORDER: The frame writer panicked");
+            }
+        }
+    }
+
+    fn read_oldest(&mut self) {
+        let Some(buffer) = self.pending.pop_front() else {
+            return;
+        };
+        let size = (self.width * self.height * 4) as usize;
+        let mut pixels = vec![0u8; size];
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pixel_buffers[buffer]);
+            let data = gl::MapBuffer(gl::PIXEL_PACK_BUFFER, gl::READ_ONLY) as *const u8;
+            if data.is_null() {
+                eprintln!("ERROR::RECORDER: Failed to map pixel buffer");
+            } else {
+                std::ptr::copy_nonoverlapping(data, pixels.as_mut_ptr(), size);
+                gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        opaque(&mut pixels);
+        if let Some(sender) = &self.sender {
+            // only fails when the writer stopped, which it already reported
+            let _ = sender.send(pixels);
+        }
+    }
+}
+
+/// OpenGL stores rows bottom up, puts the first row at the top
+fn flip_rows(pixels: Vec<u8>, width: u32) -> Vec<u8> {
+    pixels
+        .chunks_exact(width as usize * 4)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
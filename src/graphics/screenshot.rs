@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+use std::{
+    ffi::c_void,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use image::{imageops, ImageError, RgbaImage};
+
+/// Reads the pixels of a framebuffer, 0 being the window. OpenGL stores rows bottom up, so
+/// the image is flipped to have its first row at the top, and the image is made opaque.
+pub fn read_framebuffer(fbo: u32, width: u32, height: u32) -> RgbaImage {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+    opaque(&mut pixels);
+    let image = RgbaImage::from_raw(width, height, pixels).unwrap();
+    imageops::flip_vertical(&image)
+}
+
+/// Sets the alpha of RGBA pixels to 255. Blending leaves the alpha of the frame anywhere
+/// between 0 and 1, which the window ignores but image viewers show as see-through.
+pub fn opaque(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+}
+
+/// Writes an image to `directory` as a PNG named after the current time, creating the
+/// directory if needed, and returns the path of the file
+pub fn save_screenshot(image: &RgbaImage, directory: &Path) -> Result<PathBuf, ImageError> {
+    fs::create_dir_all(directory)?;
+    let file = directory.join(format!("screenshot_{}.png", timestamp()));
+    image.save(&file)?;
+    Ok(file)
+}
+
+/// Current UTC time as `YYYY-MM-DD_HH-MM-SS-mmm`, which sorts in the order files were taken
+pub fn timestamp() -> String {
+    format_timestamp(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+fn format_timestamp(since_epoch: Duration) -> String {
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let time = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}-{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Year, month and day of a number of days since 1970-01-01, from Howard Hinnant's
+/// `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(20744), (2026, 10, 18));
+        assert_eq!(civil_from_days(47846), (2100, 12, 31));
+    }
+
+    #[test]
+    fn handles_leap_years() {
+        // 2000 is a leap year, 1900 is not
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-25509), (1900, 2, 28));
+        assert_eq!(civil_from_days(-25508), (1900, 3, 1));
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let time = Duration::from_millis(20744 * 86_400_000 + 13 * 3_600_000 + 4 * 60_000 + 5_067);
+        assert_eq!(format_timestamp(time), "2026-10-18_13-04-05-067");
+        assert_eq!(format_timestamp(Duration::ZERO), "1970-01-01_00-00-00-000");
+    }
+
+    #[test]
+    fn makes_pixels_opaque() {
+        let mut pixels = vec![10, 20, 30, 0, 40, 50, 60, 128];
+        opaque(&mut pixels);
+        assert_eq!(pixels, vec![10, 20, 30, 255, 40, 50, 60, 255]);
+    }
+}
@@ -1,195 +1,371 @@
 #![allow(dead_code)]
-use std::ffi::{CStr, CString};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
 use std::ptr;
-use std::str;
+use std::rc::Rc;
 
 use gl::types::*;
 
 use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
 
+/// Value of a uniform as it was last set on a shader
+#[derive(Debug, Clone, PartialEq)]
+enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+    IntArray(Vec<i32>),
+    FloatArray(Vec<f32>),
+    Vec2Array(Vec<Vec2>),
+}
+
+impl UniformValue {
+    /// Whether the value can be assigned to a uniform of the given GLSL type
+    fn fits(&self, kind: GLenum) -> bool {
+        match self {
+            UniformValue::Int(_) | UniformValue::IntArray(_) => matches!(
+                kind,
+                gl::INT
+                    | gl::BOOL
+                    | gl::SAMPLER_2D
+                    | gl::SAMPLER_2D_ARRAY
+                    | gl::SAMPLER_2D_MULTISAMPLE
+                    | gl::SAMPLER_CUBE
+            ),
+            UniformValue::Float(_) | UniformValue::FloatArray(_) => kind == gl::FLOAT,
+            UniformValue::Vec2(_) | UniformValue::Vec2Array(_) => kind == gl::FLOAT_VEC2,
+            UniformValue::Vec3(_) => kind == gl::FLOAT_VEC3,
+            UniformValue::Vec4(_) => kind == gl::FLOAT_VEC4,
+            UniformValue::Mat4(_) => kind == gl::FLOAT_MAT4,
+        }
+    }
+}
+
+/// An active uniform of a linked program
+#[derive(Debug, Clone, Copy)]
+struct UniformInfo {
+    location: GLint,
+    kind: GLenum,
+    // number of elements for arrays, 1 otherwise
+    size: GLint,
+}
+
+#[derive(Debug)]
+struct Program {
+    id: u32,
+    // the active uniforms of the program by name, looked up once after linking
+    uniforms: HashMap<String, UniformInfo>,
+    // every uniform value set so far, set again on a new program when it is replaced
+    values: HashMap<String, UniformValue>,
+    // uniforms that were already warned about, so each warning is only printed once
+    warned: HashSet<String>,
+}
+
+/// A linked shader program. Clones share the program, so when it is reloaded every clone
+/// uses the new program, with the uniforms set on the old one carried over.
 #[derive(Debug, Clone)]
 pub struct Shader {
-    pub id: u32,
+    program: Rc<RefCell<Program>>,
 }
 
 impl Shader {
-    //pub fn new(vertex_path: &Path, fragment_path: &Path, geometry_path: Option<&Path>) -> Self {
+    /// Wraps an already linked program
+    pub fn from_program(id: u32) -> Self {
+        Self {
+            program: Rc::new(RefCell::new(Program {
+                id,
+                uniforms: unsafe { active_uniforms(id) },
+                values: HashMap::new(),
+                warned: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Compiles and links a shader program. On failure every GL object created on the way is
+    /// deleted again and the log of the stage that failed is returned.
     pub fn new(
         v_shader_code: CString,
         f_shader_code: CString,
         geometry_code: Option<CString>,
-    ) -> Self {
-        let mut shader = Shader { id: 0 };
-
+    ) -> Result<Self, CompileError> {
         unsafe {
-            // vertex shader
-            let vertex = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(vertex, 1, &v_shader_code.as_ptr(), ptr::null());
-            gl::CompileShader(vertex);
-            check_compile_errors(vertex, "VERTEX");
-
-            // fragment shader
-            let fragment = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(fragment, 1, &f_shader_code.as_ptr(), ptr::null());
-            gl::CompileShader(fragment);
-            check_compile_errors(fragment, "FRAGMENT");
-
-            // geometry shader
-            let mut geometry: u32 = 0;
-            if let Some(source) = geometry_code.as_ref() {
-                geometry = gl::CreateShader(gl::GEOMETRY_SHADER);
-                gl::ShaderSource(geometry, 1, &source.as_ptr(), ptr::null());
-                gl::CompileShader(geometry);
-                check_compile_errors(geometry, "GEOMETRY");
+            let mut stages = vec![];
+            let sources = [
+                (gl::VERTEX_SHADER, "VERTEX", Some(&v_shader_code)),
+                (gl::FRAGMENT_SHADER, "FRAGMENT", Some(&f_shader_code)),
+                (gl::GEOMETRY_SHADER, "GEOMETRY", geometry_code.as_ref()),
+            ];
+            for (kind, stage, source) in sources {
+                let Some(source) = source else {
+                    continue;
+                };
+                let shader = gl::CreateShader(kind);
+                gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+                gl::CompileShader(shader);
+                stages.push(shader);
+                if let Err(log) = check_compile_errors(shader, stage) {
+                    delete_shaders(&stages);
+                    return Err(CompileError { stage, log });
+                }
             }
 
             // shader program
-            shader.id = gl::CreateProgram();
-            gl::AttachShader(shader.id, vertex);
-            gl::AttachShader(shader.id, fragment);
-            if let Some(_source) = geometry_code.as_ref() {
-                gl::AttachShader(shader.id, geometry);
+            let id = gl::CreateProgram();
+            for shader in &stages {
+                gl::AttachShader(id, *shader);
             }
-            gl::LinkProgram(shader.id);
-            check_compile_errors(shader.id, "PROGRAM");
+            gl::LinkProgram(id);
+            let linked = check_compile_errors(id, "PROGRAM");
 
             // delete the shaders as they're linked into our program now and no longer necessary
-            gl::DeleteShader(vertex);
-            gl::DeleteShader(fragment);
-            if let Some(_source) = geometry_code {
-                gl::DeleteShader(geometry)
+            delete_shaders(&stages);
+            if let Err(log) = linked {
+                gl::DeleteProgram(id);
+                return Err(CompileError {
+                    stage: "PROGRAM",
+                    log,
+                });
             }
+
+            Ok(Shader::from_program(id))
         }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.program.borrow().id
+    }
 
-        shader
+    /// Makes this shader and all of its clones use another program and sets every uniform
+    /// on it again. Returns the id of the program that was replaced.
+    pub fn replace_program(&self, id: u32) -> u32 {
+        let old = {
+            let mut program = self.program.borrow_mut();
+            program.uniforms = unsafe { active_uniforms(id) };
+            program.warned.clear();
+            std::mem::replace(&mut program.id, id)
+        };
+        self.use_program();
+        let values: Vec<(String, UniformValue)> = self
+            .program
+            .borrow()
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for (name, value) in values {
+            self.set_uniform(&name, value);
+        }
+        unsafe {
+            gl::UseProgram(0);
+        }
+        old
     }
 
     /// activate the shader
     pub fn use_program(&self) -> &Shader {
         unsafe {
-            gl::UseProgram(self.id);
+            gl::UseProgram(self.id());
         }
         self
     }
 
-    pub unsafe fn set_bool(&self, name: &str, value: bool) {
-        gl::Uniform1i(
-            gl::GetUniformLocation(
-                self.id,
-                CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-            ),
-            value as i32,
-        );
+    /// Whether the program has an active uniform with this name. Uniforms the shader code
+    /// does not use are removed by the driver and are not active.
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.program.borrow().uniforms.contains_key(name)
     }
 
-    pub fn set_int(&self, name: &str, value: i32) {
-        unsafe {
-            gl::Uniform1i(
-                gl::GetUniformLocation(
-                    self.id,
-                    CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-                ),
-                value,
-            );
+    /// Sets a uniform of the shader, which has to be in use, and remembers its value for when
+    /// the program is replaced. Uniforms that are not active or have a different type are
+    /// skipped with a warning.
+    fn set_uniform(&self, name: &str, value: UniformValue) {
+        let mut program = self.program.borrow_mut();
+        match program.uniforms.get(name).copied() {
+            Some(info) if value.fits(info.kind) => unsafe {
+                apply_uniform(info, &value);
+            },
+            info => {
+                if !program.warned.contains(name) {
+                    match info {
+                        Some(info) => eprintln!(
+                            "WARNING::SHADER: Uniform {} of program {} has type {:#06x}, cannot set it to {:?}",
+                            name, program.id, info.kind, value
+                        ),
+                        None => eprintln!(
+                            "WARNING::SHADER: Program {} has no active uniform named {}",
+                            program.id, name
+                        ),
+                    }
+                    program.warned.insert(name.to_string());
+                }
+            }
+        }
+        match program.values.get_mut(name) {
+            Some(stored) => *stored = value,
+            None => {
+                program.values.insert(name.to_string(), value);
+            }
         }
     }
 
-    pub unsafe fn set_float(&self, name: &str, value: f32) {
-        gl::Uniform1f(
-            gl::GetUniformLocation(
-                self.id,
-                CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-            ),
-            value,
-        );
+    pub fn set_bool(&self, name: &str, value: bool) {
+        self.set_uniform(name, UniformValue::Int(value as i32));
     }
 
-    pub unsafe fn set_vec2(&self, name: &str, value: &Vec2) {
-        gl::Uniform2fv(
-            gl::GetUniformLocation(
-                self.id,
-                CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-            ),
-            1,
-            value.as_ptr(),
-        );
+    pub fn set_int(&self, name: &str, value: i32) {
+        self.set_uniform(name, UniformValue::Int(value));
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) {
+        self.set_uniform(name, UniformValue::Float(value));
+    }
+
+    pub fn set_vec2(&self, name: &str, value: &Vec2) {
+        self.set_uniform(name, UniformValue::Vec2(*value));
     }
 
-    pub unsafe fn set_vec2_xyz(&self, name: &CStr, x: f32, y: f32) {
-        gl::Uniform2f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y);
+    pub fn set_vec2_xyz(&self, name: &str, x: f32, y: f32) {
+        self.set_uniform(name, UniformValue::Vec2(Vec2::new(x, y)));
     }
 
     pub fn set_vec3(&self, name: &str, value: &Vec3) {
-        unsafe {
-            gl::Uniform3fv(
-                gl::GetUniformLocation(
-                    self.id,
-                    CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-                ),
-                1,
-                value.as_ptr(),
-            );
-        }
+        self.set_uniform(name, UniformValue::Vec3(*value));
     }
 
-    pub unsafe fn set_vec3_xyz(&self, name: &CStr, x: f32, y: f32, z: f32) {
-        gl::Uniform3f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z);
+    pub fn set_vec3_xyz(&self, name: &str, x: f32, y: f32, z: f32) {
+        self.set_uniform(name, UniformValue::Vec3(Vec3::new(x, y, z)));
     }
 
-    pub unsafe fn set_vec4(&self, name: &str, value: &Vec4) {
-        gl::Uniform4fv(
-            gl::GetUniformLocation(
-                self.id,
-                CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-            ),
-            1,
-            value.as_ptr(),
-        );
+    pub fn set_vec4(&self, name: &str, value: &Vec4) {
+        self.set_uniform(name, UniformValue::Vec4(*value));
     }
 
-    pub unsafe fn set_vec4_xyz(&self, name: &CStr, x: f32, y: f32, z: f32, w: f32) {
-        gl::Uniform4f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z, w);
+    pub fn set_vec4_xyz(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
+        self.set_uniform(name, UniformValue::Vec4(Vec4::new(x, y, z, w)));
     }
 
     pub fn set_mat4(&self, name: &str, mat: &Mat4) {
-        unsafe {
-            gl::UniformMatrix4fv(
-                // TODO: No idea if this casting is safe
-                gl::GetUniformLocation(
-                    self.id,
-                    CStr::from_bytes_with_nul_unchecked(name.as_bytes()).as_ptr(),
-                ),
-                1,
-                gl::FALSE,
-                mat.as_ptr(),
-            );
+        self.set_uniform(name, UniformValue::Mat4(*mat));
+    }
+
+    pub fn set_int_array(&self, name: &str, values: &[i32]) {
+        self.set_uniform(name, UniformValue::IntArray(values.to_vec()));
+    }
+
+    pub fn set_float_array(&self, name: &str, values: &[f32]) {
+        self.set_uniform(name, UniformValue::FloatArray(values.to_vec()));
+    }
+
+    pub fn set_vec2_array(&self, name: &str, values: &[Vec2]) {
+        self.set_uniform(name, UniformValue::Vec2Array(values.to_vec()));
+    }
+}
+
+/// Looks up the name, type and location of every active uniform of a linked program. Arrays
+/// are stored under their name without the `[0]` the driver reports them with.
+unsafe fn active_uniforms(program: u32) -> HashMap<String, UniformInfo> {
+    let mut uniforms = HashMap::new();
+    let mut count = 0;
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    for index in 0..count as GLuint {
+        let mut length = 0;
+        let mut size = 0;
+        let mut kind = 0;
+        gl::GetActiveUniform(
+            program,
+            index,
+            name.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut kind,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+        let Ok(full_name) = std::str::from_utf8(&name[..length as usize]) else {
+            continue;
+        };
+        let Ok(c_name) = CString::new(full_name) else {
+            continue;
+        };
+        let location = gl::GetUniformLocation(program, c_name.as_ptr());
+        // uniforms in blocks have no location
+        if location < 0 {
+            continue;
         }
+        let name = full_name.strip_suffix("[0]").unwrap_or(full_name);
+        uniforms.insert(
+            name.to_string(),
+            UniformInfo {
+                location,
+                kind,
+                size,
+            },
+        );
     }
+    uniforms
 }
 
-unsafe fn check_compile_errors(shader: u32, r#type: &str) {
-    let mut success = gl::FALSE as GLint;
-    let mut info_log: Vec<u8> = Vec::with_capacity(1024);
-    for val in &mut info_log {
-        *val = 0;
+unsafe fn apply_uniform(info: UniformInfo, value: &UniformValue) {
+    let location = info.location;
+    // arrays longer than the uniform would write past it
+    let count = |length: usize| (length as GLsizei).min(info.size);
+    match value {
+        UniformValue::Int(value) => gl::Uniform1i(location, *value),
+        UniformValue::Float(value) => gl::Uniform1f(location, *value),
+        UniformValue::Vec2(value) => gl::Uniform2fv(location, 1, value.as_ptr()),
+        UniformValue::Vec3(value) => gl::Uniform3fv(location, 1, value.as_ptr()),
+        UniformValue::Vec4(value) => gl::Uniform4fv(location, 1, value.as_ptr()),
+        // TODO: No idea if this casting is safe
+        UniformValue::Mat4(value) => gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr()),
+        UniformValue::IntArray(values) => {
+            gl::Uniform1iv(location, count(values.len()), values.as_ptr())
+        }
+        UniformValue::FloatArray(values) => {
+            gl::Uniform1fv(location, count(values.len()), values.as_ptr())
+        }
+        UniformValue::Vec2Array(values) => gl::Uniform2fv(
+            location,
+            count(values.len()),
+            values.as_ptr() as *const GLfloat,
+        ),
+    }
+}
+
+/// Stage of a shader that failed to compile, or `PROGRAM` when linking failed, with the
+/// log of the driver
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub stage: &'static str,
+    pub log: String,
+}
+
+unsafe fn delete_shaders(shaders: &[u32]) {
+    for shader in shaders {
+        gl::DeleteShader(*shader);
     }
+}
+
+unsafe fn check_compile_errors(shader: u32, r#type: &str) -> Result<(), String> {
+    let mut success = gl::FALSE as GLint;
+    let mut info_log = vec![0u8; 1024];
+    let mut length = 0;
 
-    info_log.set_len(1024 - 1);
     if r#type != "PROGRAM" {
         gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
         if success != gl::TRUE as GLint {
             gl::GetShaderInfoLog(
                 shader,
                 1024,
-                ptr::null_mut(),
+                &mut length,
                 info_log.as_mut_ptr() as *mut GLchar,
             );
-            println!(
-                "ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n\
-                          -- -------------------------------------------------- -- ",
-                r#type,
-                str::from_utf8(&info_log).unwrap()
-            )
         }
     } else {
         gl::GetProgramiv(shader, gl::LINK_STATUS, &mut success);
@@ -197,15 +373,15 @@ unsafe fn check_compile_errors(shader: u32, r#type: &str) {
             gl::GetProgramInfoLog(
                 shader,
                 1024,
-                ptr::null_mut(),
+                &mut length,
                 info_log.as_mut_ptr() as *mut GLchar,
             );
-            println!(
-                "ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n\
-                          -- -------------------------------------------------- -- ",
-                r#type,
-                str::from_utf8(&info_log).unwrap()
-            )
         }
     }
+
+    if success == gl::TRUE as GLint {
+        return Ok(());
+    }
+    info_log.truncate(length.max(0) as usize);
+    Err(String::from_utf8_lossy(&info_log).into_owned())
 }
@@ -1,73 +1,305 @@
 #![allow(dead_code)]
-use std::{collections::HashMap, ffi::CString, fs::File, io::Read, path::Path, rc::Rc};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
 
-use super::shader::Shader;
+use super::asset_error::AssetError;
+use super::shader::{CompileError, Shader};
+use super::shader_preprocessor::preprocess;
+
+// used in place of shaders that failed to load, draws everything it is used for in magenta.
+// The vertex shader covers the layouts with the position in pixels at location 0, like the
+// sprite batch and text, and the fullscreen passes of the effects, which are in normalized
+// device coordinates and never set a projection, leaving it all zeros.
+const FALLBACK_VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec4 vertex;
+uniform mat4 view;
+uniform mat4 projection;
+void main()
+{
+    if (projection[3][3] == 0.0)
+        gl_Position = vec4(vertex.xy, 0.0, 1.0);
+    else
+        gl_Position = projection * view * vec4(vertex.xy, 0.0, 1.0);
+}
+";
+const FALLBACK_FRAGMENT_SHADER: &str = "#version 330 core
+out vec4 color;
+void main()
+{
+    color = vec4(1.0, 0.0, 1.0, 1.0);
+}
+";
+
+// seconds between checks for changed shader files
+const RELOAD_INTERVAL: f32 = 0.5;
+
+/// Files a shader was loaded from and when they were last changed
+#[derive(Debug)]
+struct ShaderSource {
+    vertex: PathBuf,
+    fragment: PathBuf,
+    geometry: Option<PathBuf>,
+    // the stage files and every file they included the last time they were loaded
+    files: Vec<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl ShaderSource {
+    fn stages(&self) -> impl Iterator<Item = &Path> {
+        [
+            Some(&self.vertex),
+            Some(&self.fragment),
+            self.geometry.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|file| file.as_path())
+    }
+
+    /// Latest modification time of any of the files
+    fn last_modified(&self) -> Option<SystemTime> {
+        self.files
+            .iter()
+            .filter_map(|file| fs::metadata(file).and_then(|data| data.modified()).ok())
+            .max()
+    }
+}
 
 #[derive(Debug)]
 pub struct ShaderManager {
     shaders: HashMap<String, Rc<Shader>>,
+    fallback: Rc<Shader>,
+    sources: HashMap<String, ShaderSource>,
+    // added to every shader as #define lines
+    defines: Vec<(String, String)>,
+    // errors of shaders that failed to reload, by shader name
+    reload_errors: HashMap<String, String>,
+    /// Recompiles shaders whose files changed while the game is running
+    pub hot_reload: bool,
+    reload_timer: f32,
 }
 
 impl ShaderManager {
+    // compiles the fallback shader, so it needs a current OpenGL context
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             shaders: HashMap::new(),
+            fallback: fallback_shader().into(),
+            sources: HashMap::new(),
+            defines: vec![],
+            reload_errors: HashMap::new(),
+            hot_reload: cfg!(debug_assertions),
+            reload_timer: 0.0,
+        }
+    }
+
+    /// Adds `#define name value` to every shader loaded from now on, or changes the value of
+    /// a define added before. This lets constants shared with the Rust code live in one place.
+    pub fn define(&mut self, name: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.defines.iter_mut().find(|(define, _)| define == name) {
+            Some((_, current)) => *current = value,
+            None => self.defines.push((name.to_string(), value)),
         }
     }
 
+    /// Loads a shader under `name`. Its stages can `#include "file"` snippets relative to
+    /// themselves and see every define added with `define`. When loading fails a magenta
+    /// fallback is stored under the name instead, so the game keeps running, and the error
+    /// is returned.
     pub fn load_shader(
         &mut self,
         v_shader_file: &Path,
         f_shader_file: &Path,
         g_shader_file: Option<&Path>,
         name: String,
-    ) -> Rc<Shader> {
-        self.shaders.insert(
-            name.clone(),
-            load_shader_from_file(v_shader_file, f_shader_file, g_shader_file).into(),
-        );
-        // TODO: Deal with unwrap
-        self.shaders.get(&name).unwrap().clone()
+    ) -> Result<Rc<Shader>, AssetError> {
+        let mut source = ShaderSource {
+            vertex: v_shader_file.to_path_buf(),
+            fragment: f_shader_file.to_path_buf(),
+            geometry: g_shader_file.map(Path::to_path_buf),
+            files: vec![],
+            modified: None,
+        };
+        let loaded = load_shader_from_file(&mut source, &self.defines, &name);
+        source.modified = source.last_modified();
+        self.reload_errors.remove(&name);
+
+        let (shader, result) = match loaded {
+            Ok(shader) => {
+                let shader = Rc::new(shader);
+                (shader.clone(), Ok(shader))
+            }
+            // every failed shader gets a fallback program of its own, so the uniforms set on
+            // it don't leak into other failed shaders and fixing the files later only
+            // replaces the program of this one
+            Err(error) => (Rc::new(fallback_for(&source, &self.defines)), Err(error)),
+        };
+        self.sources.insert(name.clone(), source);
+        self.shaders.insert(name, shader);
+        result
+    }
+
+    /// Recompiles the shaders whose files changed since they were loaded, checking the files
+    /// every `RELOAD_INTERVAL` seconds. A shader that compiles replaces the program of the
+    /// old one, so every clone of it uses the new program. One that does not keeps the old
+    /// program and its error is kept until it compiles again.
+    pub fn update(&mut self, dt: f32) {
+        if !self.hot_reload {
+            return;
+        }
+        self.reload_timer -= dt;
+        if self.reload_timer > 0.0 {
+            return;
+        }
+        self.reload_timer = RELOAD_INTERVAL;
+
+        for (name, source) in self.sources.iter_mut() {
+            let modified = source.last_modified();
+            if modified == source.modified {
+                continue;
+            }
+            source.modified = modified;
+
+            let Some(shader) = self.shaders.get(name) else {
+                continue;
+            };
+            let reloaded = load_shader_from_file(source, &self.defines, name);
+            // includes may have been added or removed
+            source.modified = source.last_modified();
+            match reloaded {
+                Ok(reloaded) => {
+                    let old = shader.replace_program(reloaded.id());
+                    unsafe {
+                        gl::DeleteProgram(old);
+                    }
+                    self.reload_errors.remove(name);
+                }
+                Err(error) => {
+                    eprintln!("ERROR::SHADER: Failed to reload shader {}: {}", name, error);
+                    self.reload_errors.insert(name.clone(), error.to_string());
+                }
+            }
+        }
+    }
+
+    /// Errors of the shaders that failed to reload, the shaders keep their last working
+    /// program until they compile again
+    pub fn reload_errors(&self) -> impl Iterator<Item = &str> {
+        self.reload_errors.values().map(String::as_str)
     }
 
+    /// Returns the shader stored under `name`, or the fallback shader when there is none
     pub fn get_shader(&self, name: &str) -> &Shader {
-        // TODO: Deal with unwrap
-        self.shaders.get(name).unwrap()
+        self.try_get_shader(name).unwrap_or(&self.fallback)
+    }
+
+    pub fn try_get_shader(&self, name: &str) -> Result<&Shader, AssetError> {
+        self.shaders
+            .get(name)
+            .map(|shader| shader.as_ref())
+            .ok_or_else(|| AssetError::NotFound {
+                kind: "shader",
+                name: name.to_string(),
+            })
     }
 
     pub fn clear(&self) {
-        for shader in self.shaders.iter() {
+        for shader in self.shaders.values().chain([&self.fallback]) {
             unsafe {
-                gl::DeleteProgram(shader.1.id);
+                gl::DeleteProgram(shader.id());
             }
         }
     }
 }
 
+fn fallback_shader() -> Shader {
+    Shader::new(
+        CString::new(FALLBACK_VERTEX_SHADER).unwrap(),
+        CString::new(FALLBACK_FRAGMENT_SHADER).unwrap(),
+        None,
+    )
+    .expect("ERROR::SHADER: The fallback shader should always compile")
+}
+
+/// Magenta stand-in for a shader that failed to load. It keeps the vertex stage of the
+/// shader when that still compiles, so whatever it draws shows up where it would have been,
+/// and only uses the fallback vertex shader when the vertex stage is what is broken.
+fn fallback_for(source: &ShaderSource, defines: &[(String, String)]) -> Shader {
+    let vertex = preprocess(&source.vertex, defines)
+        .ok()
+        .and_then(|preprocessed| CString::new(preprocessed.code).ok());
+    let fragment = CString::new(FALLBACK_FRAGMENT_SHADER).unwrap();
+    vertex
+        .and_then(|vertex| Shader::new(vertex, fragment, None).ok())
+        .unwrap_or_else(fallback_shader)
+}
+
+/// Preprocesses and compiles the stages of a shader, recording every file they were put
+/// together from in `source` so changes to any of them can be noticed
 fn load_shader_from_file(
-    v_shader_file: &Path,
-    f_shader_file: &Path,
-    _g_shader_file: Option<&Path>,
-) -> Shader {
-    let mut v_shader_file = File::open(v_shader_file)
-        .unwrap_or_else(|_| panic!("failed to open file {}", v_shader_file.display()));
-    let mut f_shader_file = File::open(f_shader_file)
-        .unwrap_or_else(|_| panic!("failed to open file {}", f_shader_file.display()));
-
-    let mut vertex_code = String::new();
-    let mut fragment_code = String::new();
-
-    v_shader_file
-        .read_to_string(&mut vertex_code)
-        .expect("failed to read vertex shader");
-    f_shader_file
-        .read_to_string(&mut fragment_code)
-        .expect("failed to read fragment shader");
-
-    let v_shader_code =
-        CString::new(vertex_code.as_bytes()).expect("failed to read vertex code into CString");
-    let f_shader_code =
-        CString::new(fragment_code.as_bytes()).expect("failed to read fragment code into CString");
-
-    Shader::new(v_shader_code, f_shader_code, None)
+    source: &mut ShaderSource,
+    defines: &[(String, String)],
+    name: &str,
+) -> Result<Shader, AssetError> {
+    source.files = source.stages().map(Path::to_path_buf).collect();
+
+    let mut stages = vec![];
+    for file in source.files.clone() {
+        let preprocessed = preprocess(&file, defines)?;
+        for included in &preprocessed.files {
+            if !source.files.contains(included) {
+                source.files.push(included.clone());
+            }
+        }
+        let code = CString::new(preprocessed.code)
+            .map_err(|_| AssetError::InvalidSource { path: file })?;
+        stages.push((code, preprocessed.files));
+    }
+    let mut stages = stages.into_iter();
+    let (v_shader_code, v_files) = stages.next().unwrap();
+    let (f_shader_code, f_files) = stages.next().unwrap();
+    let (g_shader_code, g_files) = stages.next().unzip();
+
+    Shader::new(v_shader_code, f_shader_code, g_shader_code).map_err(
+        |CompileError { stage, log }| {
+            let files = match stage {
+                "VERTEX" => v_files,
+                "FRAGMENT" => f_files,
+                "GEOMETRY" => g_files.unwrap_or_default(),
+                _ => {
+                    return AssetError::ShaderLink {
+                        name: name.to_string(),
+                        log,
+                    }
+                }
+            };
+            AssetError::ShaderCompile {
+                path: files[0].clone(),
+                stage,
+                log: with_source_numbers(log, &files),
+            }
+        },
+    )
+}
+
+/// Lines in compile logs are prefixed by the number of the source they are in, which is
+/// only the stage file itself when it includes nothing. Lists which file each number is.
+fn with_source_numbers(log: String, files: &[PathBuf]) -> String {
+    if files.len() < 2 {
+        return log;
+    }
+    let mut log = log.trim_end().to_string();
+    log.push_str("\nsources:");
+    for (number, file) in files.iter().enumerate() {
+        log.push_str(&format!("\n  {}: {}", number, file.display()));
+    }
+    log
 }
@@ -0,0 +1,216 @@
+#![allow(dead_code)]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::asset_error::AssetError;
+
+/// Source of a shader stage after preprocessing, with every file it was put together from
+#[derive(Debug)]
+pub struct PreprocessedSource {
+    pub code: String,
+    // the stage file first, followed by the files it includes, in the order they were read.
+    // The index of a file is the source number in the `#line` directives, so compile logs
+    // can be traced back to the file a line came from.
+    pub files: Vec<PathBuf>,
+}
+
+/// Reads a shader stage and resolves its `#include "file"` directives, paths are relative to
+/// the file the directive is in. Every file is only included once, so snippets can include
+/// each other freely. `defines` are added as `#define` lines right after `#version`.
+pub fn preprocess(
+    file: &Path,
+    defines: &[(String, String)],
+) -> Result<PreprocessedSource, AssetError> {
+    let mut source = PreprocessedSource {
+        code: String::new(),
+        files: vec![],
+    };
+    let mut lines = String::new();
+    include(file, &mut lines, &mut source.files)?;
+
+    // #version has to come before anything else, so the defines go right after it
+    let (version, rest) = match lines.split_once('\n') {
+        Some((first, rest)) if first.trim_start().starts_with("#version") => (first, rest),
+        _ => ("", lines.as_str()),
+    };
+    if !version.is_empty() {
+        source.code.push_str(version);
+        source.code.push('\n');
+    }
+    for (name, value) in defines {
+        source
+            .code
+            .push_str(&format!("#define {} {}\n", name, value));
+    }
+    source.code.push_str(&format!(
+        "#line {} 0\n",
+        if version.is_empty() { 1 } else { 2 }
+    ));
+    source.code.push_str(rest);
+    Ok(source)
+}
+
+fn include(file: &Path, code: &mut String, files: &mut Vec<PathBuf>) -> Result<(), AssetError> {
+    let text = fs::read_to_string(file).map_err(|error| AssetError::Io {
+        path: file.to_path_buf(),
+        error,
+    })?;
+    let index = files.len();
+    files.push(file.to_path_buf());
+
+    for (number, line) in text.lines().enumerate() {
+        let Some(included) = include_path(line) else {
+            code.push_str(line);
+            code.push('\n');
+            continue;
+        };
+        let path = file.parent().unwrap_or(Path::new("")).join(included);
+        if !files.contains(&path) {
+            code.push_str(&format!("#line 1 {}\n", files.len()));
+            include(&path, code, files)?;
+        }
+        // continue numbering the lines of this file after the directive
+        code.push_str(&format!("#line {} {}\n", number + 2, index));
+    }
+    Ok(())
+}
+
+/// The file named by an `#include "file"` line
+fn include_path(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("#include")?
+        .trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // directory of shader files in the temp directory, removed again when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "breakout_preprocessor_{}_{}",
+                std::process::id(),
+                name
+            ));
+            fs::create_dir_all(&path).unwrap();
+            for (file, contents) in files {
+                fs::write(path.join(file), contents).unwrap();
+            }
+            Self(path)
+        }
+
+        fn file(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn numbers_lines_by_file_around_includes() {
+        let dir = TempDir::new(
+            "lines",
+            &[
+                (
+                    "main.frag",
+                    "#version 330 core\n#include \"common.glsl\"\nvoid main() {}\n",
+                ),
+                ("common.glsl", "float common;\n"),
+            ],
+        );
+
+        let source = preprocess(&dir.file("main.frag"), &[]).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 330 core\n#line 2 0\n#line 1 1\nfloat common;\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(
+            source.files,
+            vec![dir.file("main.frag"), dir.file("common.glsl")]
+        );
+    }
+
+    #[test]
+    fn includes_every_file_once() {
+        let dir = TempDir::new(
+            "once",
+            &[
+                (
+                    "main.frag",
+                    "#include \"a.glsl\"\n#include \"b.glsl\"\n#include \"a.glsl\"\n",
+                ),
+                ("a.glsl", "float a;\n"),
+                ("b.glsl", "#include \"a.glsl\"\nfloat b;\n"),
+            ],
+        );
+
+        let source = preprocess(&dir.file("main.frag"), &[]).unwrap();
+        assert_eq!(source.code.matches("float a;").count(), 1);
+        assert_eq!(source.code.matches("float b;").count(), 1);
+        assert_eq!(
+            source.files,
+            vec![
+                dir.file("main.frag"),
+                dir.file("a.glsl"),
+                dir.file("b.glsl")
+            ]
+        );
+    }
+
+    #[test]
+    fn puts_defines_after_version() {
+        let dir = TempDir::new(
+            "defines",
+            &[("main.vs", "#version 330 core\nvoid main() {}\n")],
+        );
+        let defines = [
+            ("WIDTH".to_string(), "800".to_string()),
+            ("SCALE".to_string(), "1.5".to_string()),
+        ];
+
+        let source = preprocess(&dir.file("main.vs"), &defines).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 330 core\n#define WIDTH 800\n#define SCALE 1.5\n#line 2 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn puts_defines_first_without_version() {
+        let dir = TempDir::new("no_version", &[("main.vs", "void main() {}\n")]);
+        let defines = [("WIDTH".to_string(), "800".to_string())];
+
+        let source = preprocess(&dir.file("main.vs"), &defines).unwrap();
+        assert_eq!(
+            source.code,
+            "#define WIDTH 800\n#line 1 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn fails_on_missing_include() {
+        let dir = TempDir::new(
+            "missing",
+            &[("main.frag", "#include \"missing.glsl\"\nvoid main() {}\n")],
+        );
+
+        let error = preprocess(&dir.file("main.frag"), &[]).unwrap_err();
+        assert!(
+            matches!(&error, AssetError::Io { path, .. } if *path == dir.file("missing.glsl")),
+            "unexpected error {:?}",
+            error
+        );
+    }
+}
@@ -0,0 +1,288 @@
+#![allow(dead_code)]
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::{mem, ptr};
+
+use gl::types::{GLfloat, GLsizei, GLsizeiptr, GLuint};
+use nalgebra_glm as glm;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+use super::shader::Shader;
+use super::texture::Texture2D;
+
+/// Maximum number of quads sent to the GPU in one draw call
+const MAX_QUADS: usize = 4096;
+/// vec2 position, vec2 texture coordinates and vec4 color
+const FLOATS_PER_VERTEX: usize = 8;
+const VERTICES_PER_QUAD: usize = 4;
+const INDICES_PER_QUAD: usize = 6;
+
+/// Draw order of sprites in a batch, lower layers are drawn first
+pub mod layer {
+    pub const BACKGROUND: i32 = 0;
+    pub const LEVEL: i32 = 10;
+    pub const OBJECTS: i32 = 20;
+    pub const BALL: i32 = 30;
+    pub const HUD: i32 = 40;
+}
+
+/// Number of draw calls and quads drawn since the last call to `SpriteBatch::begin_frame`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchStats {
+    pub draw_calls: u32,
+    pub quads: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Quad {
+    texture_id: GLuint,
+    layer: i32,
+    position: Vec2,
+    size: Vec2,
+    rotation: f32,
+    color: Vec4,
+    // x, y, width and height of the part of the texture to draw, in texture coordinates
+    uv_rect: Vec4,
+}
+
+/// Collects sprites and draws them in as few draw calls as possible. Sprites are sorted by
+/// layer and then by texture, so sprites sharing a texture within a layer are drawn together
+/// while keeping the order they were submitted in.
+#[derive(Debug)]
+pub struct SpriteBatch {
+    shader: Rc<Shader>,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    quads: Vec<Quad>,
+    vertices: Vec<f32>,
+    stats: BatchStats,
+}
+
+impl SpriteBatch {
+    pub fn new(shader: Rc<Shader>) -> Self {
+        let mut vao = 0;
+        let mut vbo = 0;
+        let mut ebo = 0;
+        init_render_data(&mut vao, &mut vbo, &mut ebo);
+        Self {
+            shader,
+            vao,
+            vbo,
+            ebo,
+            quads: Vec::new(),
+            vertices: Vec::with_capacity(MAX_QUADS * VERTICES_PER_QUAD * FLOATS_PER_VERTEX),
+            stats: BatchStats::default(),
+        }
+    }
+
+    /// Resets the statistics, should be called once at the start of every frame
+    pub fn begin_frame(&mut self) {
+        self.stats = BatchStats::default();
+    }
+
+    pub fn stats(&self) -> BatchStats {
+        self.stats
+    }
+
+    pub fn draw_sprite(
+        &mut self,
+        texture: &Texture2D,
+        position: Vec2,
+        size: Vec2,
+        rotate: f32,
+        color: Vec3,
+        layer: i32,
+    ) {
+        self.draw_sprite_region(
+            texture,
+            texture.uv_rect,
+            position,
+            size,
+            rotate,
+            glm::vec4(color.x, color.y, color.z, 1.0),
+            layer,
+        );
+    }
+
+    /// Queues a sprite that only uses the part of `texture` given by `uv_rect`, which is
+    /// relative to the whole GL texture rather than to an atlas region
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_sprite_region(
+        &mut self,
+        texture: &Texture2D,
+        uv_rect: Vec4,
+        position: Vec2,
+        size: Vec2,
+        rotate: f32,
+        color: Vec4,
+        layer: i32,
+    ) {
+        self.quads.push(Quad {
+            texture_id: texture.id,
+            layer,
+            position,
+            size,
+            rotation: rotate,
+            color,
+            uv_rect,
+        });
+    }
+
+    /// Draws every queued sprite
+    pub fn flush(&mut self) {
+        if self.quads.is_empty() {
+            return;
+        }
+
+        // stable sort, so sprites with the same layer and texture keep their submission order
+        self.quads.sort_by_key(|quad| (quad.layer, quad.texture_id));
+
+        self.shader.use_program();
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.vao);
+        }
+
+        let mut start = 0;
+        while start < self.quads.len() {
+            // find the run of quads sharing the same texture
+            let texture_id = self.quads[start].texture_id;
+            let mut end = start;
+            while end < self.quads.len()
+                && end - start < MAX_QUADS
+                && self.quads[end].texture_id == texture_id
+            {
+                end += 1;
+            }
+
+            self.vertices.clear();
+            for i in start..end {
+                push_quad_vertices(&mut self.vertices, &self.quads[i]);
+            }
+
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture_id);
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    0,
+                    (self.vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                    self.vertices.as_ptr() as *const c_void,
+                );
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    ((end - start) * INDICES_PER_QUAD) as GLsizei,
+                    gl::UNSIGNED_INT,
+                    ptr::null(),
+                );
+            }
+
+            self.stats.draw_calls += 1;
+            self.stats.quads += (end - start) as u32;
+            start = end;
+        }
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        self.quads.clear();
+    }
+}
+
+fn push_quad_vertices(vertices: &mut Vec<f32>, quad: &Quad) {
+    // rotate the corners around the center of the quad
+    let center = quad.position + quad.size * 0.5;
+    let (sin, cos) = quad.rotation.to_radians().sin_cos();
+    let half = quad.size * 0.5;
+
+    #[rustfmt::skip]
+    let corners: [(f32, f32); VERTICES_PER_QUAD] = [
+        (-1.0, -1.0), // top left
+        ( 1.0, -1.0), // top right
+        ( 1.0,  1.0), // bottom right
+        (-1.0,  1.0), // bottom left
+    ];
+
+    for (cx, cy) in corners {
+        let local = glm::vec2(cx * half.x, cy * half.y);
+        let x = center.x + local.x * cos - local.y * sin;
+        let y = center.y + local.x * sin + local.y * cos;
+        let u = quad.uv_rect.x + (cx + 1.0) * 0.5 * quad.uv_rect.z;
+        let v = quad.uv_rect.y + (cy + 1.0) * 0.5 * quad.uv_rect.w;
+        vertices.extend_from_slice(&[
+            x,
+            y,
+            u,
+            v,
+            quad.color.x,
+            quad.color.y,
+            quad.color.z,
+            quad.color.w,
+        ]);
+    }
+}
+
+fn init_render_data(vao: &mut GLuint, vbo: &mut GLuint, ebo: &mut GLuint) {
+    // every quad uses the same index pattern, so the index buffer never changes
+    let mut indices: Vec<u32> = Vec::with_capacity(MAX_QUADS * INDICES_PER_QUAD);
+    for i in 0..MAX_QUADS as u32 {
+        let base = i * VERTICES_PER_QUAD as u32;
+        indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+    }
+
+    unsafe {
+        gl::GenVertexArrays(1, vao);
+        gl::GenBuffers(1, vbo);
+        gl::GenBuffers(1, ebo);
+
+        gl::BindVertexArray(*vao);
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, *vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (MAX_QUADS * VERTICES_PER_QUAD * FLOATS_PER_VERTEX * mem::size_of::<GLfloat>())
+                as GLsizeiptr,
+            ptr::null(),
+            gl::DYNAMIC_DRAW,
+        );
+
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, *ebo);
+        gl::BufferData(
+            gl::ELEMENT_ARRAY_BUFFER,
+            (indices.len() * mem::size_of::<u32>()) as GLsizeiptr,
+            indices.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        );
+
+        let stride = (FLOATS_PER_VERTEX * mem::size_of::<GLfloat>()) as GLsizei;
+        // position
+        gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, ptr::null());
+        gl::EnableVertexAttribArray(0);
+        // texture coordinates
+        gl::VertexAttribPointer(
+            1,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            (2 * mem::size_of::<GLfloat>()) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(1);
+        // color
+        gl::VertexAttribPointer(
+            2,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            (4 * mem::size_of::<GLfloat>()) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(2);
+
+        // the element buffer binding is part of the VAO state, so only unbind the array buffer
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        gl::BindVertexArray(0);
+    }
+}
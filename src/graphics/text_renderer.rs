@@ -1,170 +1,443 @@
-use std::{collections::HashMap, ffi::c_void};
+use std::{ffi::c_void, path::Path};
 
-use freetype as ft;
 use gl::types::{GLfloat, GLsizei, GLsizeiptr};
 use nalgebra_glm as glm;
 
+use super::asset_error::AssetError;
+use super::font::Font;
+use super::font_manager::FontManager;
 use super::shader::Shader;
 
-struct Character {
-    texture_id: u32,
-    size: glm::IVec2,
-    bearing: glm::IVec2,
-    advance: u32,
+// position and texture coordinates of a vertex
+const FLOATS_PER_VERTEX: usize = 4;
+
+/// Horizontal alignment of text inside the box it is laid out in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Outline, drop shadow and glow of text drawn with `TextRenderer::render_text_sdf`. None of
+/// them can reach further than the spread of the distance field font.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextEffects {
+    /// Colour and width in pixels of a line around the glyphs
+    pub outline: Option<(glm::Vec4, f32)>,
+    /// Colour and offset in pixels of a copy of the text drawn behind it
+    pub shadow: Option<(glm::Vec4, glm::Vec2)>,
+    /// Colour and width in pixels of a soft halo around the glyphs
+    pub glow: Option<(glm::Vec4, f32)>,
 }
 
+/// Draws text with the current font. The `scale` of every call picks the pixel size the
+/// font is rasterized at, so scaled text stays as sharp as text drawn at its base size.
 pub struct TextRenderer {
-    characters: HashMap<u8, Character>,
+    pub font_manager: FontManager,
+    font: String,
+    font_size: u32,
+    // multiplier of the line height of the font
+    pub line_spacing: f32,
     text_shader: Shader,
+    sdf_shader: Shader,
     vao: u32,
     vbo: u32,
+    vertices: Vec<f32>,
 }
 
 impl TextRenderer {
-    pub fn new(width: u32, height: u32, text_shader: Shader) -> Self {
+    pub fn new(projection: &glm::Mat4, text_shader: Shader, sdf_shader: Shader) -> Self {
         let mut vao = 0;
         let mut vbo = 0;
 
-        text_shader.use_program();
-        // TODO: No clue whether the near and far values here makes sense
-        text_shader.set_mat4(
-            "projection\0",
-            &glm::ortho(0.0, width as f32, height as f32, 0.0, 0.0, 1.0),
-        );
-        text_shader.set_int("text\0", 0);
+        for shader in [&text_shader, &sdf_shader] {
+            shader.use_program();
+            shader.set_mat4("projection", projection);
+            // text is fixed on the screen unless given a camera view
+            shader.set_mat4("view", &glm::Mat4::identity());
+            shader.set_int("text", 0);
+        }
 
-        // configure VAO/VBO for texture quads
+        // configure VAO/VBO for texture quads, the buffer is sized when text is drawn
         unsafe {
             gl::GenVertexArrays(1, &mut vao);
             gl::GenBuffers(1, &mut vbo);
             gl::BindVertexArray(vao);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (std::mem::size_of::<GLfloat>() * 6 * 4) as GLsizeiptr,
-                std::ptr::null(),
-                gl::DYNAMIC_DRAW,
-            );
             gl::EnableVertexAttribArray(0);
-            let stride = 4 * std::mem::size_of::<GLfloat>() as GLsizei;
+            let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<GLfloat>()) as GLsizei;
             gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
         }
 
         Self {
-            characters: HashMap::new(),
+            font_manager: FontManager::new(),
+            font: String::new(),
+            font_size: 0,
+            line_spacing: 1.0,
             text_shader,
+            sdf_shader,
             vao,
             vbo,
+            vertices: vec![],
         }
     }
 
-    pub fn load(&mut self, font: String, font_size: u32) {
-        self.characters.clear();
-        let library =
-            ft::Library::init().expect("ERROR::FREETYPE: Could not init FreeType Library");
-        let face = library
-            .new_face(font, 0)
-            .expect("ERROR::FREETYPE: Failed to load font");
-        face.set_pixel_sizes(0, font_size).unwrap();
+    /// View matrix of the following calls, the camera's view for text in the world or the
+    /// identity for text fixed on the screen
+    pub fn set_view(&self, view: &glm::Mat4) {
+        for shader in [&self.text_shader, &self.sdf_shader] {
+            shader.use_program().set_mat4("view", view);
+        }
+    }
 
-        unsafe {
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-            for c in 0..128 {
-                // TODO: Should we not panic here? Just continue
-                face.load_char(c, ft::face::LoadFlag::RENDER).unwrap();
-
-                // generate texture
-                let mut texture = 0;
-                gl::GenTextures(1, &mut texture);
-                gl::BindTexture(gl::TEXTURE_2D, texture);
-                //gl::TexImage2D(target, level, internalformat, width, height, border, format, type_, pixels)
-                gl::TexImage2D(
-                    gl::TEXTURE_2D,
-                    0,
-                    gl::RED as i32,
-                    face.glyph().bitmap().width(),
-                    face.glyph().bitmap().rows(),
-                    0,
-                    gl::RED,
-                    gl::UNSIGNED_BYTE,
-                    face.glyph().bitmap().buffer().as_ptr() as *const c_void,
-                );
-
-                // set texture options
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-                // now store character for later use
-                let character = Character {
-                    texture_id: texture,
-                    size: glm::IVec2::new(
-                        face.glyph().bitmap().width(),
-                        face.glyph().bitmap().rows(),
-                    ),
-                    bearing: glm::IVec2::new(face.glyph().bitmap_left(), face.glyph().bitmap_top()),
-                    advance: face.glyph().advance().x as u32,
-                };
-                self.characters.insert(c as u8, character);
-            }
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+    /// Loads a font file and makes it the current font at `font_size` pixels. Without a
+    /// font nothing is drawn, but the game keeps running.
+    pub fn load(&mut self, font: &Path, name: &str, font_size: u32) -> Result<(), AssetError> {
+        let result = self.font_manager.load_font(font, name);
+        self.set_font(name, font_size);
+        result
+    }
+
+    /// Selects the font and base size used by the following calls
+    pub fn set_font(&mut self, name: &str, font_size: u32) {
+        self.font = name.to_string();
+        self.font_size = font_size;
+    }
+
+    fn scaled_font(&mut self, scale: f32) -> Option<&mut Font> {
+        let size = (self.font_size as f32 * scale).round().max(1.0) as u32;
+        self.font_manager.get_font(&self.font, size)
+    }
+
+    /// Distance between the tops of two lines of text
+    pub fn line_height(&mut self, scale: f32) -> f32 {
+        let line_spacing = self.line_spacing;
+        self.scaled_font(scale)
+            .map_or(0.0, |font| font.line_height() * line_spacing)
+    }
+
+    /// Width of a single line of text
+    pub fn measure_line(&mut self, line: &str, scale: f32) -> f32 {
+        self.scaled_font(scale)
+            .map_or(0.0, |font| line_width(font, line, 1.0))
+    }
+
+    /// Width of the widest line and total height of the text
+    pub fn measure(&mut self, text: &str, scale: f32) -> glm::Vec2 {
+        let mut width: f32 = 0.0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            width = width.max(self.measure_line(line, scale));
+            lines += 1;
+        }
+        glm::vec2(width, lines as f32 * self.line_height(scale))
+    }
+
+    /// Breaks text into lines no wider than `max_width`, at spaces where possible. A word
+    /// wider than `max_width` is put on a line of its own.
+    pub fn wrap(&mut self, text: &str, max_width: f32, scale: f32) -> Vec<String> {
+        match self.scaled_font(scale) {
+            Some(font) => wrap_lines(font, text, max_width, 1.0),
+            None => text.split('\n').map(str::to_string).collect(),
         }
     }
 
-    pub fn render_text(&mut self, text: &str, mut x: f32, y: f32, scale: f32, color: glm::Vec3) {
-        // activate corresponding render state
+    /// Draws text wrapped to fit a box `width` pixels wide with its top left corner at
+    /// `x`, `y`, aligning every line inside the box
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_text_aligned(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        width: f32,
+        scale: f32,
+        color: glm::Vec3,
+        align: TextAlign,
+    ) {
+        let line_spacing = self.line_spacing;
+        let mut vertices = std::mem::take(&mut self.vertices);
+        vertices.clear();
+        let Some(font) = self.scaled_font(scale) else {
+            self.vertices = vertices;
+            return;
+        };
+        let lines = wrap_lines(font, text, width, 1.0);
+        let line_height = font.line_height() * line_spacing;
+        queue_aligned(
+            font,
+            &mut vertices,
+            &lines,
+            x,
+            y,
+            width,
+            align,
+            line_height,
+            1.0,
+        );
+        let texture_id = font.texture();
+        self.vertices = vertices;
+
         self.text_shader.use_program();
-        self.text_shader.set_vec3("textColor\0", &color);
+        self.text_shader.set_vec3("textColor", &color);
+        self.draw(texture_id);
+    }
+
+    /// Draws UTF-8 text with its top left corner at `x`, `y`. Every `\n` starts a new line.
+    pub fn render_text(&mut self, text: &str, x: f32, y: f32, scale: f32, color: glm::Vec3) {
+        let line_height = self.line_height(scale);
+        let mut vertices = std::mem::take(&mut self.vertices);
+        vertices.clear();
+        let Some(font) = self.scaled_font(scale) else {
+            self.vertices = vertices;
+            return;
+        };
+        let mut line_y = y;
+        for line in text.split('\n') {
+            queue_line(font, &mut vertices, line, x, line_y, 1.0);
+            line_y += line_height;
+        }
+        let texture_id = font.texture();
+        self.vertices = vertices;
+
+        self.text_shader.use_program();
+        self.text_shader.set_vec3("textColor", &color);
+        self.draw(texture_id);
+    }
+
+    /// Like `render_text_aligned`, but draws the glyphs from the distance field version of
+    /// the current font. The text stays sharp at any scale, which makes it suited for text
+    /// that grows and shrinks every frame, and can have an outline, a shadow and a glow.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_text_sdf(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        width: f32,
+        scale: f32,
+        color: glm::Vec3,
+        align: TextAlign,
+        effects: &TextEffects,
+    ) {
+        let (font_size, line_spacing) = (self.font_size as f32, self.line_spacing);
+        let mut vertices = std::mem::take(&mut self.vertices);
+        vertices.clear();
+        let Some(font) = self.font_manager.get_distance_field_font(&self.font) else {
+            self.vertices = vertices;
+            return;
+        };
+        let glyph_scale = font_size * scale / font.size() as f32;
+        let lines = wrap_lines(font, text, width, glyph_scale);
+        let line_height = font.line_height() * glyph_scale * line_spacing;
+        queue_aligned(
+            font,
+            &mut vertices,
+            &lines,
+            x,
+            y,
+            width,
+            align,
+            line_height,
+            glyph_scale,
+        );
+        let (texture_id, spread) = (font.texture(), font.spread());
+        self.vertices = vertices;
+
+        // one pixel on screen in the units of the distance field texture
+        let distance_per_pixel = 64.0 / 255.0 / (spread as f32 * glyph_scale);
+        let (outline_color, outline_width) = effects.outline.unwrap_or_default();
+        let (shadow_color, shadow_offset) = effects.shadow.unwrap_or_default();
+        let (glow_color, glow_width) = effects.glow.unwrap_or_default();
+
+        let shader = &self.sdf_shader;
+        shader.use_program();
+        shader.set_vec3("textColor", &color);
+        shader.set_float("distancePerPixel", distance_per_pixel);
+        shader.set_float("glyphScale", glyph_scale);
+        shader.set_vec4("outlineColor", &outline_color);
+        shader.set_float("outlineWidth", outline_width);
+        shader.set_vec4("shadowColor", &shadow_color);
+        shader.set_vec2("shadowOffset", &shadow_offset);
+        shader.set_vec4("glowColor", &glow_color);
+        shader.set_float("glowWidth", glow_width);
+        self.draw(texture_id);
+    }
+
+    /// Draws the queued vertices with the shader that is in use
+    fn draw(&mut self, texture_id: u32) {
+        if self.vertices.is_empty() {
+            return;
+        }
 
+        // every glyph is in the same texture, so the whole text is a single draw call
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
             gl::BindVertexArray(self.vao);
-
-            // iterate through all characters
-            for c in text.as_bytes() {
-                let ch = &self.characters.get(c).unwrap();
-                let x_pos = x + ch.bearing.x as f32 * scale;
-                let y_pos = y
-                    + (self.characters.get(&b'H').unwrap().bearing.y - ch.bearing.y) as f32 * scale;
-
-                let w = ch.size.x as f32 * scale;
-                let h = ch.size.y as f32 * scale;
-
-                // update VBO for each character
-                #[rustfmt::skip]
-                let vertices: [[f32;4]; 6] = [
-                    [x_pos,     y_pos + h, 0.0, 1.0],
-                    [x_pos + w, y_pos,     1.0, 0.0],
-                    [x_pos,     y_pos,     0.0, 0.0],
-
-                    [x_pos,     y_pos + h, 0.0, 1.0],
-                    [x_pos + w, y_pos + h, 1.0, 1.0],
-                    [x_pos + w, y_pos,     1.0, 0.0],
-                ];
-
-                // render glyph texture over quad
-                gl::BindTexture(gl::TEXTURE_2D, ch.texture_id);
-                // update content of VBO memory
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-                gl::BufferSubData(
-                    gl::ARRAY_BUFFER,
-                    0,
-                    std::mem::size_of_val(&vertices) as GLsizeiptr,
-                    vertices.as_ptr() as *const c_void,
-                );
-                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-                // update quad
-                gl::DrawArrays(gl::TRIANGLES, 0, 6);
-
-                // now advance cursors for next glyph
-                x += (ch.advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels
-            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.vertices.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr,
+                self.vertices.as_ptr() as *const c_void,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::DrawArrays(
+                gl::TRIANGLES,
+                0,
+                (self.vertices.len() / FLOATS_PER_VERTEX) as GLsizei,
+            );
             gl::BindVertexArray(0);
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
     }
 }
+
+/// Width of a line of text, with the glyphs of `font` scaled by `glyph_scale`
+fn line_width(font: &mut Font, line: &str, glyph_scale: f32) -> f32 {
+    let mut width = 0.0;
+    let mut previous = 0;
+    for c in line.chars() {
+        let ch = font.character(c);
+        width += font.kerning(previous, ch.glyph_index);
+        width += (ch.advance >> 6) as f32;
+        previous = ch.glyph_index;
+    }
+    width * glyph_scale
+}
+
+fn wrap_lines(font: &mut Font, text: &str, max_width: f32, glyph_scale: f32) -> Vec<String> {
+    let space = line_width(font, " ", glyph_scale);
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut current_width = 0.0;
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let word_width = line_width(font, word, glyph_scale);
+            if !line.is_empty() && current_width + space + word_width > max_width {
+                lines.push(std::mem::take(&mut line));
+                current_width = 0.0;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+                current_width += space;
+            }
+            line.push_str(word);
+            current_width += word_width;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Adds the quads of lines of text aligned inside a box `width` pixels wide
+#[allow(clippy::too_many_arguments)]
+fn queue_aligned(
+    font: &mut Font,
+    vertices: &mut Vec<f32>,
+    lines: &[String],
+    x: f32,
+    y: f32,
+    width: f32,
+    align: TextAlign,
+    line_height: f32,
+    glyph_scale: f32,
+) {
+    let mut line_y = y;
+    for line in lines {
+        let line_x = match align {
+            TextAlign::Left => x,
+            TextAlign::Center => x + (width - line_width(font, line, glyph_scale)) / 2.0,
+            TextAlign::Right => x + width - line_width(font, line, glyph_scale),
+        };
+        queue_line(font, vertices, line, line_x, line_y, glyph_scale);
+        line_y += line_height;
+    }
+}
+
+/// Adds the quads of a single line of text with its top left corner at `x`, `y`
+fn queue_line(
+    font: &mut Font,
+    vertices: &mut Vec<f32>,
+    line: &str,
+    x: f32,
+    y: f32,
+    glyph_scale: f32,
+) {
+    // a new glyph can grow the atlas, which changes the texture coordinates of every glyph
+    // before it, so all of them are added before any quad is made
+    for c in line.chars().filter(|&c| c != '\r') {
+        font.character(c);
+    }
+
+    let mut cursor_x = x;
+    let mut previous = 0;
+    for c in line.chars() {
+        if c == '\r' {
+            continue;
+        }
+
+        let ch = font.character(c);
+        cursor_x += font.kerning(previous, ch.glyph_index) * glyph_scale;
+        previous = ch.glyph_index;
+
+        let x_pos = cursor_x + ch.bearing.x as f32 * glyph_scale;
+        let y_pos = y + (font.ascent() - ch.bearing.y as f32) * glyph_scale;
+
+        let w = ch.size.x as f32 * glyph_scale;
+        let h = ch.size.y as f32 * glyph_scale;
+        let uv = font.uv_rect(&ch);
+        let (u0, v0, u1, v1) = (uv.x, uv.y, uv.x + uv.z, uv.y + uv.w);
+
+        #[rustfmt::skip]
+        vertices.extend_from_slice(&[
+            x_pos,     y_pos + h, u0, v1,
+            x_pos + w, y_pos,     u1, v0,
+            x_pos,     y_pos,     u0, v0,
+
+            x_pos,     y_pos + h, u0, v1,
+            x_pos + w, y_pos + h, u1, v1,
+            x_pos + w, y_pos,     u1, v0,
+        ]);
+
+        // now advance cursors for next glyph
+        cursor_x += (ch.advance >> 6) as f32 * glyph_scale; // bitshift by 6 to get value in pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // floats of a quad before the vertex holding the top left texture coordinates
+    const TOP_LEFT: usize = 2 * FLOATS_PER_VERTEX;
+
+    #[test]
+    fn texture_coordinates_hold_when_a_line_grows_the_atlas() {
+        let library = freetype::Library::init().unwrap();
+        let mut font = Font::new(&library, Path::new("resources/fonts/OCRAEXT.TTF"), 48);
+        let a = font.character('A');
+        let before = font.uv_rect(&a);
+
+        // none of these are rasterized up front, and together they do not fit in the atlas
+        let line: String = ('\u{C0}'..='\u{FF}').collect();
+        let mut vertices = vec![];
+        queue_line(&mut font, &mut vertices, &line, 0.0, 0.0, 1.0);
+
+        let after = font.uv_rect(&a);
+        assert!(after.y < before.y, "the atlas should have grown");
+
+        // every quad uses the texture coordinates of the final atlas
+        let quad_floats = vertices.len() / line.chars().count();
+        for (i, c) in line.chars().enumerate() {
+            let ch = font.character(c);
+            let uv = font.uv_rect(&ch);
+            let quad = &vertices[i * quad_floats + TOP_LEFT..];
+            assert_eq!((quad[2], quad[3]), (uv.x, uv.y), "{:?}", c);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::ffi::c_void;
 
 use gl::types::{GLenum, GLint, GLuint};
+use nalgebra_glm as glm;
 
 #[derive(Debug, Clone)]
 pub struct Texture2D {
@@ -9,13 +10,18 @@ pub struct Texture2D {
     height: u32,
     pub internal_format: GLint,
     pub image_format: GLenum,
-    wrap_s: GLenum,
-    wrap_t: GLenum,
-    filter_min: GLint,
-    filter_max: GLint,
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub filter_min: GLint,
+    pub filter_max: GLint,
+    // x, y, width and height of the part of the GL texture this texture covers, in texture
+    // coordinates. Only differs from the whole texture for sprites packed into an atlas.
+    pub uv_rect: glm::Vec4,
 }
 
 impl Texture2D {
+    // creates an OpenGL texture, so it needs a current context
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let mut texture = 0;
         unsafe {
@@ -32,9 +38,33 @@ impl Texture2D {
             wrap_t: gl::REPEAT,
             filter_min: gl::LINEAR as i32,
             filter_max: gl::LINEAR as i32,
+            uv_rect: glm::vec4(0.0, 0.0, 1.0, 1.0),
         }
     }
 
+    /// Returns a texture sharing the same GL texture that only covers the given rectangle
+    /// in pixels
+    pub fn region(&self, x: u32, y: u32, width: u32, height: u32) -> Texture2D {
+        let mut region = self.clone();
+        region.width = width;
+        region.height = height;
+        region.uv_rect = glm::vec4(
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            width as f32 / self.width as f32,
+            height as f32 / self.height as f32,
+        );
+        region
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
     pub fn generate(&mut self, width: i32, height: i32, data: &[u8]) {
         self.width = width as u32;
         self.height = height as u32;
@@ -69,8 +99,8 @@ impl Texture2D {
             }
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, self.wrap_s as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, self.wrap_t as i32);
-            gl::TextureParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, self.filter_min);
-            gl::TextureParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, self.filter_max);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, self.filter_min);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, self.filter_max);
 
             gl::GenerateMipmap(gl::TEXTURE_2D);
 
@@ -0,0 +1,367 @@
+#![allow(dead_code)]
+use std::{fs, path::Path};
+
+use image::{GenericImage, RgbaImage};
+
+use super::asset_error::AssetError;
+
+/// Location of a packed image inside an atlas page, in pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One atlas image together with the regions packed into it
+#[derive(Debug)]
+pub struct AtlasPage {
+    pub image: RgbaImage,
+    pub regions: Vec<AtlasRegion>,
+}
+
+impl AtlasPage {
+    /// Writes the page image and a manifest that `load_manifest` can read back, so a packed
+    /// atlas can be shipped instead of the separate images.
+    pub fn save(&self, image_file: &Path, manifest_file: &Path) {
+        if let Err(error) = self.image.save(image_file) {
+            eprintln!(
+                "ERROR::ATLAS: Failed to save {}: {}",
+                image_file.display(),
+                error
+            );
+        }
+
+        let mut manifest = String::new();
+        for region in &self.regions {
+            manifest.push_str(&format!(
+                "{} {} {} {} {}\n",
+                region.name, region.x, region.y, region.width, region.height
+            ));
+        }
+        if let Err(error) = fs::write(manifest_file, manifest) {
+            eprintln!(
+                "ERROR::ATLAS: Failed to save {}: {}",
+                manifest_file.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Packs images into square atlas pages using rows ("shelves") of images sorted by height.
+///
+/// Every image is surrounded by `padding` pixels. With `bleed` enabled the padding is filled
+/// with the edge pixels of the image instead of being left transparent, so linear filtering
+/// at the edge of a sprite never picks up its neighbour.
+#[derive(Debug)]
+pub struct AtlasBuilder {
+    size: u32,
+    padding: u32,
+    bleed: bool,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new(size: u32, padding: u32, bleed: bool) -> Self {
+        Self {
+            size,
+            padding,
+            bleed,
+            images: vec![],
+        }
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        if image.width() == 0 || image.height() == 0 {
+            eprintln!("ERROR::ATLAS: {} is empty", name);
+            return;
+        }
+        self.images.push((name.to_string(), image));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn build(mut self) -> Vec<AtlasPage> {
+        // tallest images first keeps the shelves tight
+        self.images
+            .sort_by(|a, b| b.1.height().cmp(&a.1.height()).then(a.0.cmp(&b.0)));
+
+        let mut pages: Vec<AtlasPage> = vec![];
+        let mut oversized: Vec<AtlasPage> = vec![];
+        // position of the next image and height of the current shelf on the last page
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+        for (name, image) in &self.images {
+            let width = image.width() + 2 * self.padding;
+            let height = image.height() + 2 * self.padding;
+
+            if width > self.size || height > self.size {
+                // too big to share a page, so it gets a page of its own
+                eprintln!(
+                    "ERROR::ATLAS: {} is larger than the atlas size {}",
+                    name, self.size
+                );
+                let mut page = AtlasPage {
+                    image: RgbaImage::new(width, height),
+                    regions: vec![],
+                };
+                self.blit(&mut page, name, image, 0, 0);
+                oversized.push(page);
+                continue;
+            }
+
+            if x + width > self.size {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            if pages.is_empty() || y + height > self.size {
+                pages.push(AtlasPage {
+                    image: RgbaImage::new(self.size, self.size),
+                    regions: vec![],
+                });
+                x = 0;
+                y = 0;
+                shelf_height = 0;
+            }
+
+            let page = pages.last_mut().unwrap();
+            self.blit(page, name, image, x, y);
+            x += width;
+            shelf_height = shelf_height.max(height);
+        }
+
+        pages.append(&mut oversized);
+        pages
+    }
+
+    fn blit(&self, page: &mut AtlasPage, name: &str, image: &RgbaImage, x: u32, y: u32) {
+        let padding = self.padding;
+        if self.bleed {
+            // every pixel of the padded area takes the colour of the closest image pixel
+            for dy in 0..image.height() + 2 * padding {
+                for dx in 0..image.width() + 2 * padding {
+                    let sx = dx.saturating_sub(padding).min(image.width() - 1);
+                    let sy = dy.saturating_sub(padding).min(image.height() - 1);
+                    page.image
+                        .put_pixel(x + dx, y + dy, *image.get_pixel(sx, sy));
+                }
+            }
+        } else {
+            page.image
+                .copy_from(image, x + padding, y + padding)
+                .expect("Image should fit inside the atlas page");
+        }
+
+        page.regions.push(AtlasRegion {
+            name: name.to_string(),
+            x: x + padding,
+            y: y + padding,
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+}
+
+/// Reads an atlas manifest, where every line is `name x y width height` in pixels
+pub fn load_manifest(file: &Path) -> Result<Vec<AtlasRegion>, AssetError> {
+    let contents = fs::read_to_string(file).map_err(|error| AssetError::Io {
+        path: file.to_path_buf(),
+        error,
+    })?;
+
+    let mut regions = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<u32> = parts
+            .iter()
+            .skip(1)
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        if parts.len() != 5 || numbers.len() != 4 {
+            eprintln!("ERROR::ATLAS: Invalid manifest line {}", line);
+            continue;
+        }
+        regions.push(AtlasRegion {
+            name: parts[0].to_string(),
+            x: numbers[0],
+            y: numbers[1],
+            width: numbers[2],
+            height: numbers[3],
+        });
+    }
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn image(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(color))
+    }
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("breakout_atlas_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn packed_regions_do_not_overlap_and_keep_their_padding() {
+        let padding = 2;
+        let mut builder = AtlasBuilder::new(64, padding, false);
+        for (i, (width, height)) in [(20, 10), (12, 12), (30, 6), (8, 20), (16, 16), (10, 4)]
+            .into_iter()
+            .enumerate()
+        {
+            builder.add_image(&format!("image{}", i), image(width, height, [255; 4]));
+        }
+        let pages = builder.build();
+        assert_eq!(pages.len(), 1);
+
+        let regions = &pages[0].regions;
+        assert_eq!(regions.len(), 6);
+        // the padded areas have to fit on the page without touching each other
+        let padded: Vec<(u32, u32, u32, u32)> = regions
+            .iter()
+            .map(|r| {
+                assert!(r.x >= padding && r.y >= padding);
+                assert!(r.x + r.width + padding <= 64 && r.y + r.height + padding <= 64);
+                (
+                    r.x - padding,
+                    r.y - padding,
+                    r.x + r.width + padding,
+                    r.y + r.height + padding,
+                )
+            })
+            .collect();
+        for (i, a) in padded.iter().enumerate() {
+            for b in &padded[i + 1..] {
+                let overlap = a.0 < b.2 && b.0 < a.2 && a.1 < b.3 && b.1 < a.3;
+                assert!(!overlap, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_images_get_a_page_of_their_own() {
+        let mut builder = AtlasBuilder::new(32, 1, false);
+        builder.add_image("small", image(8, 8, [255; 4]));
+        builder.add_image("wide", image(40, 10, [255; 4]));
+        let pages = builder.build();
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].image.dimensions(), (32, 32));
+        assert_eq!(pages[0].regions[0].name, "small");
+        assert_eq!(pages[1].image.dimensions(), (42, 12));
+        assert_eq!(
+            pages[1].regions,
+            vec![AtlasRegion {
+                name: "wide".to_string(),
+                x: 1,
+                y: 1,
+                width: 40,
+                height: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn bleed_copies_the_edge_pixels_into_the_padding() {
+        // a 2x2 image with a different colour in every corner
+        let mut corners = RgbaImage::new(2, 2);
+        corners.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        corners.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        corners.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        corners.put_pixel(1, 1, Rgba([255, 255, 0, 255]));
+
+        let mut builder = AtlasBuilder::new(16, 2, true);
+        builder.add_image("corners", corners.clone());
+        let page = &builder.build()[0];
+        let region = &page.regions[0];
+        assert_eq!((region.x, region.y), (2, 2));
+
+        // the padding takes the colour of the closest image pixel
+        assert_eq!(page.image.get_pixel(0, 0), corners.get_pixel(0, 0));
+        assert_eq!(page.image.get_pixel(5, 0), corners.get_pixel(1, 0));
+        assert_eq!(page.image.get_pixel(0, 5), corners.get_pixel(0, 1));
+        assert_eq!(page.image.get_pixel(5, 5), corners.get_pixel(1, 1));
+        assert_eq!(page.image.get_pixel(2, 1), corners.get_pixel(0, 0));
+        assert_eq!(page.image.get_pixel(4, 3), corners.get_pixel(1, 1));
+        // and the image itself is copied unchanged
+        assert_eq!(page.image.get_pixel(3, 2), corners.get_pixel(1, 0));
+
+        // without bleed the padding stays transparent
+        let mut builder = AtlasBuilder::new(16, 2, false);
+        builder.add_image("corners", corners.clone());
+        let page = &builder.build()[0];
+        assert_eq!(page.image.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(page.image.get_pixel(2, 2), corners.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn saved_manifest_loads_the_same_regions() {
+        let mut builder = AtlasBuilder::new(32, 1, false);
+        builder.add_image("ball", image(8, 8, [255; 4]));
+        builder.add_image("paddle", image(20, 4, [255; 4]));
+        let page = &builder.build()[0];
+
+        let image_file = temp_file("round_trip.png");
+        let manifest_file = temp_file("round_trip.atlas");
+        page.save(&image_file, &manifest_file);
+        let regions = load_manifest(&manifest_file).unwrap();
+        let saved = image::open(&image_file).map(|image| image.to_rgba8());
+        let _ = fs::remove_file(&image_file);
+        let _ = fs::remove_file(&manifest_file);
+
+        assert_eq!(regions, page.regions);
+        assert_eq!(saved.unwrap(), page.image);
+    }
+
+    #[test]
+    fn skips_malformed_manifest_lines() {
+        let manifest_file = temp_file("malformed.atlas");
+        fs::write(
+            &manifest_file,
+            "# name x y width height\n\
+             \n\
+             ball 1 1 8 8\n\
+             missing 1 2 3\n\
+             not_a_number 1 2 three 4\n\
+             too_many 1 2 3 4 5\n\
+             negative -1 2 3 4\n\
+             paddle 10 1 20 4\n",
+        )
+        .unwrap();
+        let regions = load_manifest(&manifest_file).unwrap();
+        let _ = fs::remove_file(&manifest_file);
+
+        assert!(matches!(
+            load_manifest(&manifest_file),
+            Err(AssetError::Io { .. })
+        ));
+
+        let names: Vec<&str> = regions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["ball", "paddle"]);
+        assert_eq!(
+            regions[1],
+            AtlasRegion {
+                name: "paddle".to_string(),
+                x: 10,
+                y: 1,
+                width: 20,
+                height: 4,
+            }
+        );
+    }
+}
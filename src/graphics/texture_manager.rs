@@ -1,40 +1,191 @@
 #![allow(dead_code)]
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
+use image::{DynamicImage, RgbaImage};
+
+use super::asset_error::AssetError;
 use super::texture::Texture2D;
+use super::texture_atlas::{load_manifest, AtlasBuilder, AtlasRegion};
+
+// size in pixels of the fallback texture and of its squares
+const CHECKERBOARD_SIZE: u32 = 64;
+const CHECKERBOARD_SQUARE: u32 = 8;
 
 #[derive(Debug, Clone)]
 pub struct TextureManager {
     textures: HashMap<String, Texture2D>,
+    // textures holding the packed sprites, the sprites themselves are in `textures`
+    atlases: Vec<Texture2D>,
+    // images waiting to be packed by `build_atlases`
+    queued: Vec<(String, RgbaImage)>,
+    // magenta and black checkerboard used in place of textures that failed to load
+    fallback: Texture2D,
 }
 
 impl TextureManager {
+    // uploads the fallback texture, so it needs a current OpenGL context
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        let checkerboard = checkerboard();
+        let mut fallback = Texture2D::new();
+        fallback.internal_format = gl::RGBA as i32;
+        fallback.image_format = gl::RGBA;
+        fallback.generate(
+            checkerboard.width() as i32,
+            checkerboard.height() as i32,
+            checkerboard.as_raw(),
+        );
+
         Self {
             textures: HashMap::new(),
+            atlases: vec![],
+            queued: vec![],
+            fallback,
         }
     }
 
-    pub fn load_texture(&mut self, file: &Path, alpha: bool, name: &str) -> &Texture2D {
-        self.textures
-            .insert(name.to_string(), load_texture_from_file(file, alpha));
+    /// Loads a texture under `name`. When loading fails the checkerboard texture is stored
+    /// under the name instead, so the game keeps running, and the error is returned.
+    pub fn load_texture(
+        &mut self,
+        file: &Path,
+        alpha: bool,
+        name: &str,
+    ) -> Result<&Texture2D, AssetError> {
+        let (texture, result) = match load_texture_from_file(file, alpha) {
+            Ok(texture) => (texture, Ok(())),
+            Err(error) => (self.fallback.clone(), Err(error)),
+        };
+        self.textures.insert(name.to_string(), texture);
+        result.map(|_| self.get_texture(name))
+    }
+
+    /// Creates a texture from raw pixel data, e.g. for textures generated at runtime
+    pub fn generate_texture(
+        &mut self,
+        width: i32,
+        height: i32,
+        data: &[u8],
+        alpha: bool,
+        name: &str,
+    ) -> &Texture2D {
+        let mut texture = Texture2D::new();
+        if alpha {
+            texture.internal_format = gl::RGBA as i32;
+            texture.image_format = gl::RGBA;
+        }
+        texture.generate(width, height, data);
+        self.textures.insert(name.to_string(), texture);
         self.textures.get(name).unwrap()
     }
 
+    /// Queues an image file to be packed into an atlas by `build_atlases`. An image that
+    /// fails to load is replaced by the checkerboard.
+    pub fn queue_atlas_texture(&mut self, file: &Path, name: &str) -> Result<(), AssetError> {
+        let (image, result) = match open_image(file) {
+            Ok(image) => (image.to_rgba8(), Ok(())),
+            Err(error) => (checkerboard(), Err(error)),
+        };
+        self.queued.push((name.to_string(), image));
+        result
+    }
+
+    /// Queues raw RGBA pixel data to be packed into an atlas by `build_atlases`
+    pub fn queue_atlas_data(&mut self, width: u32, height: u32, data: &[u8], name: &str) {
+        let image = RgbaImage::from_raw(width, height, data.to_vec())
+            .expect("Pixel data should match the given size");
+        self.queued.push((name.to_string(), image));
+    }
+
+    /// Packs every queued image into as few atlases of `size` x `size` pixels as possible.
+    /// The packed sprites are available under their own names like any other texture.
+    pub fn build_atlases(&mut self, size: u32, padding: u32, bleed: bool) {
+        let mut builder = AtlasBuilder::new(size, padding, bleed);
+        for (name, image) in self.queued.drain(..) {
+            builder.add_image(&name, image);
+        }
+
+        for page in builder.build() {
+            self.add_atlas(
+                page.image.width(),
+                page.image.height(),
+                page.image.as_raw(),
+                &page.regions,
+            );
+        }
+    }
+
+    /// Loads a prebuilt atlas image together with the manifest describing its sprites
+    pub fn load_atlas(&mut self, file: &Path, manifest: &Path) -> Result<(), AssetError> {
+        let image = open_image(file)?.to_rgba8();
+        let regions = load_manifest(manifest)?;
+        self.add_atlas(image.width(), image.height(), image.as_raw(), &regions);
+        Ok(())
+    }
+
+    fn add_atlas(&mut self, width: u32, height: u32, data: &[u8], regions: &[AtlasRegion]) {
+        let mut atlas = Texture2D::new();
+        atlas.internal_format = gl::RGBA as i32;
+        atlas.image_format = gl::RGBA;
+        // repeating would sample the opposite edge of the atlas
+        atlas.wrap_s = gl::CLAMP_TO_EDGE;
+        atlas.wrap_t = gl::CLAMP_TO_EDGE;
+        atlas.generate(width as i32, height as i32, data);
+
+        for region in regions {
+            self.textures.insert(
+                region.name.clone(),
+                atlas.region(region.x, region.y, region.width, region.height),
+            );
+        }
+        self.atlases.push(atlas);
+    }
+
+    pub fn atlas_count(&self) -> usize {
+        self.atlases.len()
+    }
+
+    /// Returns the texture stored under `name`, or the checkerboard when there is none
     pub fn get_texture(&self, name: &str) -> &Texture2D {
-        self.textures.get(name).unwrap()
+        self.try_get_texture(name).unwrap_or(&self.fallback)
+    }
+
+    pub fn try_get_texture(&self, name: &str) -> Result<&Texture2D, AssetError> {
+        self.textures.get(name).ok_or_else(|| AssetError::NotFound {
+            kind: "texture",
+            name: name.to_string(),
+        })
     }
 
     pub fn clear(&self) {
-        for texture in self.textures.iter() {
+        // sprites in an atlas share its GL texture, so every id is only deleted once
+        let ids: HashSet<u32> = self
+            .textures
+            .values()
+            .chain(self.atlases.iter())
+            .chain([&self.fallback])
+            .map(|texture| texture.id)
+            .collect();
+        for id in ids {
             unsafe {
-                gl::DeleteTextures(1, &texture.1.id);
+                gl::DeleteTextures(1, &id);
             }
         }
     }
 }
 
-fn load_texture_from_file(file: &Path, alpha: bool) -> Texture2D {
+fn load_texture_from_file(file: &Path, alpha: bool) -> Result<Texture2D, AssetError> {
+    let image = open_image(file)?;
+    // convert the image to the layout the texture is created with
+    let data = if alpha {
+        image.to_rgba8().into_raw()
+    } else {
+        image.to_rgb8().into_raw()
+    };
+
     let mut texture = Texture2D::new();
 
     if alpha {
@@ -42,12 +193,30 @@ fn load_texture_from_file(file: &Path, alpha: bool) -> Texture2D {
         texture.image_format = gl::RGBA;
     }
 
-    let image = image::open(file).unwrap();
-    texture.generate(
-        image.width() as i32,
-        image.height() as i32,
-        image.as_bytes(),
-    );
+    texture.generate(image.width() as i32, image.height() as i32, &data);
+
+    Ok(texture)
+}
+
+fn open_image(file: &Path) -> Result<DynamicImage, AssetError> {
+    image::open(file).map_err(|error| match error {
+        image::ImageError::IoError(error) => AssetError::Io {
+            path: file.to_path_buf(),
+            error,
+        },
+        error => AssetError::Image {
+            path: file.to_path_buf(),
+            error,
+        },
+    })
+}
 
-    texture
+fn checkerboard() -> RgbaImage {
+    RgbaImage::from_fn(CHECKERBOARD_SIZE, CHECKERBOARD_SIZE, |x, y| {
+        if (x / CHECKERBOARD_SQUARE + y / CHECKERBOARD_SQUARE) % 2 == 1 {
+            image::Rgba([0, 0, 0, 255])
+        } else {
+            image::Rgba([255, 0, 255, 255])
+        }
+    })
 }
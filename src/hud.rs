@@ -0,0 +1,102 @@
+#![allow(dead_code)]
+use nalgebra_glm as glm;
+
+use crate::{
+    graphics::{
+        sprite_batch::{layer, SpriteBatch},
+        text_renderer::TextRenderer,
+        texture_manager::TextureManager,
+    },
+    powerup::PowerUp,
+};
+
+const ICON_SIZE: glm::Vec2 = glm::Vec2::new(48.0, 16.0);
+const BAR_HEIGHT: f32 = 4.0;
+const SLOT_SPACING: f32 = 8.0;
+const MARGIN: f32 = 5.0;
+// seconds before expiry at which the icon starts blinking
+const BLINK_TIME: f32 = 3.0;
+const BLINKS_PER_SECOND: f32 = 4.0;
+
+/// Shows the power-ups that are currently active in the top right corner, each with a
+/// bar counting down its remaining duration.
+#[derive(Debug)]
+pub struct Hud {
+    width: u32,
+    time: f32,
+}
+
+impl Hud {
+    pub fn new(width: u32) -> Self {
+        Self { width, time: 0.0 }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn draw(
+        &self,
+        powerups: &[PowerUp],
+        renderer: &mut SpriteBatch,
+        texture_manager: &TextureManager,
+        text_renderer: &mut TextRenderer,
+    ) {
+        let mut x = self.width as f32 - MARGIN - ICON_SIZE.x;
+        for powerup in powerups {
+            // powerups without a duration take effect immediately, so there is nothing to show
+            if !powerup.activated || powerup.total_duration <= 0.0 {
+                continue;
+            }
+
+            let expiring = powerup.duration <= BLINK_TIME;
+            let blink_off = expiring && (self.time * BLINKS_PER_SECOND).fract() < 0.5;
+            if !blink_off {
+                renderer.draw_sprite(
+                    texture_manager.get_texture(&powerup.object.sprite_id),
+                    glm::vec2(x, MARGIN),
+                    ICON_SIZE,
+                    0.0,
+                    powerup.object.color,
+                    layer::HUD,
+                );
+            }
+
+            // background and remaining part of the countdown bar
+            let bar_position = glm::vec2(x, MARGIN + ICON_SIZE.y + 2.0);
+            let remaining = (powerup.duration / powerup.total_duration).clamp(0.0, 1.0);
+            let bar_color = if expiring {
+                glm::vec3(1.0, 0.3, 0.3)
+            } else {
+                powerup.object.color
+            };
+            renderer.draw_sprite(
+                texture_manager.get_texture("white"),
+                bar_position,
+                glm::vec2(ICON_SIZE.x, BAR_HEIGHT),
+                0.0,
+                glm::vec3(0.2, 0.2, 0.2),
+                layer::HUD,
+            );
+            renderer.draw_sprite(
+                texture_manager.get_texture("white"),
+                bar_position,
+                glm::vec2(ICON_SIZE.x * remaining, BAR_HEIGHT),
+                0.0,
+                bar_color,
+                layer::HUD,
+            );
+
+            text_renderer.render_text(
+                &format!("{}", powerup.duration.ceil() as u32),
+                x,
+                bar_position.y + BAR_HEIGHT + 3.0,
+                0.5,
+                bar_color,
+            );
+
+            x -= ICON_SIZE.x + SLOT_SPACING;
+        }
+        renderer.flush();
+    }
+}
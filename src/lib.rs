@@ -1,7 +1,11 @@
+mod audio;
 mod ball;
-mod game;
+mod definitions;
+pub mod game;
 mod game_level;
 mod game_object;
-mod graphics;
+pub mod graphics;
+mod hud;
 mod particle_generator;
 mod powerup;
+pub mod settings;
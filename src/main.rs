@@ -7,8 +7,12 @@ extern crate image;
 
 use std::sync::mpsc::Receiver;
 
+mod audio;
+
 mod ball;
 
+mod definitions;
+
 mod graphics;
 use gl::BLEND;
 use graphics::{shader_manager, texture_manager, Graphics};
@@ -22,8 +26,11 @@ mod game_object;
 
 mod game_level;
 
+mod hud;
+
 mod particle_generator;
 mod powerup;
+mod settings;
 
 // settings
 const SCR_WIDTH: u32 = 800;
@@ -83,6 +90,9 @@ pub fn main() {
         let current_frame = glfw.get_time();
         delta_time = current_frame - last_frame;
         last_frame = current_frame;
+        if let Some(time_step) = game.fixed_time_step() {
+            delta_time = time_step;
+        }
         glfw.poll_events();
 
         // manage user input
@@ -158,6 +168,34 @@ fn process_events(
                 game.keys[glfw::Key::Enter as usize] = false;
                 game.keys_processed[glfw::Key::Enter as usize] = false;
             }
+            glfw::WindowEvent::Key(Key::P, _, Action::Press, _) => {
+                game.keys[glfw::Key::P as usize] = true;
+            }
+            glfw::WindowEvent::Key(Key::P, _, Action::Release, _) => {
+                game.keys[glfw::Key::P as usize] = false;
+                game.keys_processed[glfw::Key::P as usize] = false;
+            }
+            glfw::WindowEvent::Key(Key::F3, _, Action::Press, _) => {
+                game.keys[glfw::Key::F3 as usize] = true;
+            }
+            glfw::WindowEvent::Key(Key::F3, _, Action::Release, _) => {
+                game.keys[glfw::Key::F3 as usize] = false;
+                game.keys_processed[glfw::Key::F3 as usize] = false;
+            }
+            glfw::WindowEvent::Key(Key::F10, _, Action::Press, _) => {
+                game.keys[glfw::Key::F10 as usize] = true;
+            }
+            glfw::WindowEvent::Key(Key::F10, _, Action::Release, _) => {
+                game.keys[glfw::Key::F10 as usize] = false;
+                game.keys_processed[glfw::Key::F10 as usize] = false;
+            }
+            glfw::WindowEvent::Key(Key::F12, _, Action::Press, _) => {
+                game.keys[glfw::Key::F12 as usize] = true;
+            }
+            glfw::WindowEvent::Key(Key::F12, _, Action::Release, _) => {
+                game.keys[glfw::Key::F12 as usize] = false;
+                game.keys_processed[glfw::Key::F12 as usize] = false;
+            }
             glfw::WindowEvent::Key(Key::Space, _, Action::Press, _) => {
                 game.keys[glfw::Key::Space as usize] = true;
             }
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use std::ffi::c_void;
+use std::{collections::HashMap, ffi::c_void, fs, path::Path};
 
 use gl::types::*;
 
@@ -7,6 +7,7 @@ use nalgebra_glm as glm;
 use rand::prelude::*;
 
 use crate::{
+    definitions::{parse_floats, parse_sections, parse_vec2, parse_vec4, Properties},
     game_object::GameObject,
     graphics::{shader::Shader, texture::Texture2D},
 };
@@ -16,7 +17,16 @@ pub struct Particle {
     position: glm::Vec2,
     velocity: glm::Vec2,
     color: glm::Vec4,
+    size: f32,
     life: f32,
+    // state needed to animate the particle over its lifetime
+    max_life: f32,
+    gravity: glm::Vec2,
+    tint: glm::Vec4,
+    start_color: glm::Vec4,
+    end_color: glm::Vec4,
+    start_size: f32,
+    end_size: f32,
 }
 
 impl Particle {
@@ -25,11 +35,46 @@ impl Particle {
             position: glm::vec2(0.0, 0.0),
             velocity: glm::vec2(0.0, 0.0),
             color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            size: 10.0,
             life: 0.0,
+            max_life: 0.0,
+            gravity: glm::vec2(0.0, 0.0),
+            tint: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            start_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            end_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            start_size: 10.0,
+            end_size: 10.0,
         }
     }
 }
 
+/// Where inside the emitter new particles appear
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    Circle {
+        radius: f32,
+    },
+    /// Anywhere inside the area of the object that emits the particles, e.g. a whole brick
+    Area,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmitterDefinition {
+    pub shape: EmitterShape,
+    pub burst: u32,
+    pub min_life: f32,
+    pub max_life: f32,
+    pub velocity: glm::Vec2,
+    // maximum speed added in a random direction on top of `velocity`
+    pub spread: f32,
+    pub gravity: glm::Vec2,
+    pub start_color: glm::Vec4,
+    pub end_color: glm::Vec4,
+    pub start_size: f32,
+    pub end_size: f32,
+}
+
 #[derive(Debug)]
 pub struct ParticleGenerator {
     particles: Vec<Particle>,
@@ -37,10 +82,17 @@ pub struct ParticleGenerator {
     shader: Shader,
     texture: Texture2D,
     vao: u32,
+    // per instance offset, color and scale of every live particle, uploaded each frame
+    instance_vbo: u32,
+    instance_data: Vec<f32>,
     last_used_particle: usize,
     rand: ThreadRng,
+    emitters: HashMap<String, EmitterDefinition>,
 }
 
+/// Number of floats per particle in the instance buffer: vec2 offset, vec4 color and float scale
+const INSTANCE_FLOATS: usize = 7;
+
 impl ParticleGenerator {
     pub fn new(shader: Shader, texture: Texture2D, amount: u32) -> Self {
         Self {
@@ -49,8 +101,62 @@ impl ParticleGenerator {
             shader,
             texture,
             vao: 0,
+            instance_vbo: 0,
+            instance_data: Vec::new(),
             last_used_particle: 0,
             rand: rand::thread_rng(),
+            emitters: HashMap::new(),
+        }
+    }
+
+    /// Loads named emitter definitions from a file made up of sections like
+    ///
+    /// ```text
+    /// [paddle_hit]
+    /// shape = circle
+    /// radius = 4.0
+    /// burst = 12
+    /// lifetime = 0.2 0.4
+    /// velocity = 0.0 -60.0
+    /// spread = 120.0
+    /// gravity = 0.0 300.0
+    /// start_color = 1.0 1.0 1.0 1.0
+    /// end_color = 1.0 1.0 1.0 0.0
+    /// size = 8.0 2.0
+    /// ```
+    ///
+    /// where `shape` is one of `point`, `circle` or `area`.
+    pub fn load_emitters(&mut self, file: &Path) {
+        let contents =
+            fs::read_to_string(file).expect("Should have been able to read the emitter file");
+        for (name, properties) in parse_sections(&contents) {
+            match parse_emitter(&properties) {
+                Some(emitter) => {
+                    self.emitters.insert(name, emitter);
+                }
+                None => eprintln!("ERROR::PARTICLES: Invalid emitter definition {}", name),
+            }
+        }
+    }
+
+    /// Spawns a burst of particles from the named emitter. `size` is the area used by
+    /// emitters with an `Area` shape and `tint` is multiplied with the emitter's colors.
+    pub fn emit(&mut self, name: &str, position: glm::Vec2, size: glm::Vec2, tint: glm::Vec3) {
+        let Some(emitter) = self.emitters.get(name) else {
+            eprintln!("ERROR::PARTICLES: Unknown emitter {}", name);
+            return;
+        };
+
+        for _ in 0..emitter.burst {
+            let unused_particle = Self::first_unused_particle(
+                &mut self.last_used_particle,
+                self.amount,
+                &self.particles,
+            );
+
+            if let Some(particle) = self.particles.get_mut(unused_particle) {
+                Self::spawn_particle(particle, emitter, position, size, tint, &mut self.rand);
+            }
         }
     }
 
@@ -74,28 +180,66 @@ impl ParticleGenerator {
                 particle.life -= dt;
                 if particle.life > 0.0 {
                     // particle is alive, thus update
-                    particle.position -= particle.velocity * dt;
-                    particle.color.w -= dt * 2.5;
+                    let t = 1.0 - particle.life / particle.max_life;
+                    particle.velocity += particle.gravity * dt;
+                    particle.position += particle.velocity * dt;
+                    particle.color = glm::lerp(&particle.start_color, &particle.end_color, t)
+                        .component_mul(&particle.tint);
+                    particle.size = glm::lerp_scalar(particle.start_size, particle.end_size, t);
                 }
             }
         }
     }
 
-    pub fn draw(&self) {
+    pub fn draw(&mut self) {
+        // gather all live particles into the instance buffer
+        self.instance_data.clear();
+        for particle in &self.particles {
+            if particle.life > 0.0 {
+                self.instance_data.extend_from_slice(&[
+                    particle.position.x,
+                    particle.position.y,
+                    particle.color.x,
+                    particle.color.y,
+                    particle.color.z,
+                    particle.color.w,
+                    particle.size,
+                ]);
+            }
+        }
+        let instances = self.instance_data.len() / INSTANCE_FLOATS;
+        if instances == 0 {
+            return;
+        }
+
         // use additive blending to give it a 'glow' effect
         unsafe {
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
             self.shader.use_program();
-            for particle in &self.particles {
-                if particle.life > 0.0 {
-                    self.shader.set_vec2("offset\0", &particle.position);
-                    self.shader.set_vec4("color\0", &particle.color);
-                    self.texture.bind();
-                    gl::BindVertexArray(self.vao);
-                    gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                    gl::BindVertexArray(0);
-                }
-            }
+
+            // orphan the old buffer storage so the driver doesn't have to wait for
+            // the previous frame's draw call before we can write to it again
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.amount as usize * INSTANCE_FLOATS * std::mem::size_of::<f32>()) as GLsizeiptr,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                (self.instance_data.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
+                self.instance_data.as_ptr() as *const c_void,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            self.texture.bind();
+            gl::BindVertexArray(self.vao);
+            gl::DrawArraysInstanced(gl::TRIANGLES, 0, 6, instances as GLsizei);
+            gl::BindVertexArray(0);
+
             // don't forget to reset to default blending mode
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
@@ -138,8 +282,49 @@ impl ParticleGenerator {
                 4 * std::mem::size_of::<f32>() as GLsizei,
                 std::ptr::null(),
             );
+
+            // set per instance attributes, these advance once per particle instead of per vertex
+            gl::GenBuffers(1, &mut self.instance_vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.amount as usize * INSTANCE_FLOATS * std::mem::size_of::<f32>()) as GLsizeiptr,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+            let stride = (INSTANCE_FLOATS * std::mem::size_of::<f32>()) as GLsizei;
+            // offset
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+            // color
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(
+                2,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const c_void,
+            );
+            gl::VertexAttribDivisor(2, 1);
+            // scale
+            gl::EnableVertexAttribArray(3);
+            gl::VertexAttribPointer(
+                3,
+                1,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (6 * std::mem::size_of::<f32>()) as *const c_void,
+            );
+            gl::VertexAttribDivisor(3, 1);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
 
+            self.instance_data
+                .reserve(self.amount as usize * INSTANCE_FLOATS);
             for _ in 0..self.amount {
                 self.particles.push(Particle::new());
             }
@@ -185,8 +370,219 @@ impl ParticleGenerator {
         particle.position.x = object.position.x + random_val + offset.x;
         particle.position.y = object.position.y + random_val + offset.y;
 
-        particle.color = glm::vec4(random_color, random_color, random_color, 1.0);
-        particle.life = 1.0;
-        particle.velocity = object.velocity * 0.1;
+        // trail particles fade out at a constant rate of 2.5 per second
+        particle.start_color = glm::vec4(random_color, random_color, random_color, 1.0);
+        particle.end_color = glm::vec4(random_color, random_color, random_color, 0.0);
+        particle.color = particle.start_color;
+        particle.tint = glm::vec4(1.0, 1.0, 1.0, 1.0);
+        particle.life = 0.4;
+        particle.max_life = particle.life;
+        particle.velocity = -object.velocity * 0.1;
+        particle.gravity = glm::vec2(0.0, 0.0);
+        particle.start_size = 10.0;
+        particle.end_size = 10.0;
+        particle.size = particle.start_size;
+    }
+
+    fn spawn_particle(
+        particle: &mut Particle,
+        emitter: &EmitterDefinition,
+        position: glm::Vec2,
+        size: glm::Vec2,
+        tint: glm::Vec3,
+        thread_rng: &mut ThreadRng,
+    ) {
+        let spawn_offset = match emitter.shape {
+            EmitterShape::Point => glm::vec2(0.0, 0.0),
+            EmitterShape::Circle { radius } => {
+                let angle = thread_rng.gen_range(0.0..std::f32::consts::TAU);
+                let distance = radius * thread_rng.gen::<f32>().sqrt();
+                glm::vec2(angle.cos(), angle.sin()) * distance
+            }
+            EmitterShape::Area => glm::vec2(
+                thread_rng.gen::<f32>() * size.x,
+                thread_rng.gen::<f32>() * size.y,
+            ),
+        };
+
+        let angle = thread_rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = thread_rng.gen::<f32>() * emitter.spread;
+
+        // particles are drawn from their top left corner, so center them on the spawn point
+        particle.position = position + spawn_offset - glm::vec2(0.5, 0.5) * emitter.start_size;
+        particle.velocity = emitter.velocity + glm::vec2(angle.cos(), angle.sin()) * speed;
+        particle.gravity = emitter.gravity;
+        particle.life = if emitter.max_life > emitter.min_life {
+            thread_rng.gen_range(emitter.min_life..emitter.max_life)
+        } else {
+            emitter.min_life
+        };
+        particle.max_life = particle.life;
+        particle.tint = glm::vec4(tint.x, tint.y, tint.z, 1.0);
+        particle.start_color = emitter.start_color;
+        particle.end_color = emitter.end_color;
+        particle.color = emitter.start_color.component_mul(&particle.tint);
+        particle.start_size = emitter.start_size;
+        particle.end_size = emitter.end_size;
+        particle.size = emitter.start_size;
+    }
+}
+
+fn parse_emitter(properties: &Properties) -> Option<EmitterDefinition> {
+    let shape = match properties.get("shape").map(|shape| shape.as_str()) {
+        Some("point") | None => EmitterShape::Point,
+        Some("circle") => EmitterShape::Circle {
+            radius: properties.get("radius")?.parse().ok()?,
+        },
+        Some("area") => EmitterShape::Area,
+        Some(_) => return None,
+    };
+
+    let (min_life, max_life) = match parse_floats(properties.get("lifetime")?)?.as_slice() {
+        [life] => (*life, *life),
+        [min, max] => (*min, *max),
+        _ => return None,
+    };
+
+    let (start_size, end_size) = match properties.get("size") {
+        Some(size) => match parse_floats(size)?.as_slice() {
+            [size] => (*size, *size),
+            [start, end] => (*start, *end),
+            _ => return None,
+        },
+        None => (10.0, 10.0),
+    };
+
+    let start_color = parse_vec4(properties.get("start_color")?)?;
+    Some(EmitterDefinition {
+        shape,
+        burst: properties.get("burst")?.parse().ok()?,
+        min_life,
+        max_life,
+        velocity: match properties.get("velocity") {
+            Some(velocity) => parse_vec2(velocity)?,
+            None => glm::vec2(0.0, 0.0),
+        },
+        spread: match properties.get("spread") {
+            Some(spread) => spread.parse().ok()?,
+            None => 0.0,
+        },
+        gravity: match properties.get("gravity") {
+            Some(gravity) => parse_vec2(gravity)?,
+            None => glm::vec2(0.0, 0.0),
+        },
+        start_color,
+        end_color: match properties.get("end_color") {
+            Some(color) => parse_vec4(color)?,
+            None => start_color,
+        },
+        start_size,
+        end_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter(lines: &str) -> Option<EmitterDefinition> {
+        let (_, properties) = parse_sections(&format!("[test]\n{}", lines)).remove(0);
+        parse_emitter(&properties)
+    }
+
+    #[test]
+    fn parses_every_shape() {
+        let point = emitter("shape = point\nburst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1");
+        assert_eq!(point.unwrap().shape, EmitterShape::Point);
+
+        let circle = emitter(
+            "shape = circle\nradius = 4.5\nburst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1",
+        );
+        assert_eq!(circle.unwrap().shape, EmitterShape::Circle { radius: 4.5 });
+
+        let area = emitter("shape = area\nburst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1");
+        assert_eq!(area.unwrap().shape, EmitterShape::Area);
+
+        // without a shape particles start from a single point
+        let default = emitter("burst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1");
+        assert_eq!(default.unwrap().shape, EmitterShape::Point);
+    }
+
+    #[test]
+    fn rejects_unknown_shapes_and_circles_without_radius() {
+        assert!(
+            emitter("shape = cone\nburst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1").is_none()
+        );
+        assert!(
+            emitter("shape = circle\nburst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1").is_none()
+        );
+    }
+
+    #[test]
+    fn parses_burst_lifetime_and_motion() {
+        let emitter = emitter(
+            "burst = 12\n\
+             lifetime = 0.2 0.4\n\
+             velocity = 0.0 -60.0\n\
+             spread = 120.0\n\
+             gravity = 0.0 300.0\n\
+             start_color = 1 1 1 1",
+        )
+        .unwrap();
+        assert_eq!(emitter.burst, 12);
+        assert_eq!((emitter.min_life, emitter.max_life), (0.2, 0.4));
+        assert_eq!(emitter.velocity, glm::vec2(0.0, -60.0));
+        assert_eq!(emitter.spread, 120.0);
+        assert_eq!(emitter.gravity, glm::vec2(0.0, 300.0));
+    }
+
+    #[test]
+    fn parses_colour_and_size_over_life() {
+        let emitter = emitter(
+            "burst = 1\n\
+             lifetime = 1.0\n\
+             start_color = 1.0 0.5 0.0 1.0\n\
+             end_color = 1.0 1.0 1.0 0.0\n\
+             size = 8.0 2.0",
+        )
+        .unwrap();
+        assert_eq!(emitter.start_color, glm::vec4(1.0, 0.5, 0.0, 1.0));
+        assert_eq!(emitter.end_color, glm::vec4(1.0, 1.0, 1.0, 0.0));
+        assert_eq!((emitter.start_size, emitter.end_size), (8.0, 2.0));
+    }
+
+    #[test]
+    fn single_values_hold_over_the_whole_life() {
+        let emitter =
+            emitter("burst = 1\nlifetime = 0.5\nstart_color = 1 0 0 1\nsize = 6.0").unwrap();
+        assert_eq!((emitter.min_life, emitter.max_life), (0.5, 0.5));
+        assert_eq!(emitter.end_color, emitter.start_color);
+        assert_eq!((emitter.start_size, emitter.end_size), (6.0, 6.0));
+    }
+
+    #[test]
+    fn optional_keys_fall_back_to_defaults() {
+        let emitter = emitter("burst = 3\nlifetime = 1.0\nstart_color = 1 1 1 1").unwrap();
+        assert_eq!(emitter.velocity, glm::vec2(0.0, 0.0));
+        assert_eq!(emitter.spread, 0.0);
+        assert_eq!(emitter.gravity, glm::vec2(0.0, 0.0));
+        assert_eq!((emitter.start_size, emitter.end_size), (10.0, 10.0));
+    }
+
+    #[test]
+    fn rejects_missing_required_keys() {
+        assert!(emitter("lifetime = 1.0\nstart_color = 1 1 1 1").is_none());
+        assert!(emitter("burst = 1\nstart_color = 1 1 1 1").is_none());
+        assert!(emitter("burst = 1\nlifetime = 1.0").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert!(emitter("burst = many\nlifetime = 1.0\nstart_color = 1 1 1 1").is_none());
+        assert!(emitter("burst = 1\nlifetime = 1 2 3\nstart_color = 1 1 1 1").is_none());
+        assert!(emitter("burst = 1\nlifetime = 1.0\nstart_color = 1 1 1").is_none());
+        assert!(
+            emitter("burst = 1\nlifetime = 1.0\nstart_color = 1 1 1 1\nsize = 1 2 3").is_none()
+        );
     }
 }
@@ -1,11 +1,18 @@
+#![allow(dead_code)]
+use std::{collections::HashMap, fs, path::Path};
+
 use nalgebra_glm as glm;
+use rand::Rng;
 
-use crate::game_object::GameObject;
+use crate::{
+    definitions::{parse_sections, parse_vec3, Properties},
+    game_object::GameObject,
+};
 
 const POWERUP_SIZE: glm::Vec2 = glm::Vec2::new(60.0, 20.0);
 const POWERUP_VELOCITY: glm::Vec2 = glm::Vec2::new(0.0, 150.0);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PowerUpType {
     Speed,
     Sticky,
@@ -13,6 +20,66 @@ pub enum PowerUpType {
     PadSizeIncrease,
     Confuse,
     Chaos,
+    Laser,
+    Shrink,
+    SlowBall,
+    ExtraLife,
+    Shield,
+    Magnet,
+}
+
+impl PowerUpType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "speed" => Some(PowerUpType::Speed),
+            "sticky" => Some(PowerUpType::Sticky),
+            "passthrough" => Some(PowerUpType::PassThrough),
+            "pad_size_increase" => Some(PowerUpType::PadSizeIncrease),
+            "confuse" => Some(PowerUpType::Confuse),
+            "chaos" => Some(PowerUpType::Chaos),
+            "laser" => Some(PowerUpType::Laser),
+            "shrink" => Some(PowerUpType::Shrink),
+            "slow_ball" => Some(PowerUpType::SlowBall),
+            "extra_life" => Some(PowerUpType::ExtraLife),
+            "shield" => Some(PowerUpType::Shield),
+            "magnet" => Some(PowerUpType::Magnet),
+            _ => None,
+        }
+    }
+}
+
+/// What happens when a power-up is picked up while one of the same type is still active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stacking {
+    /// The running power-up starts over with its full duration
+    Refresh,
+    /// The duration of the new power-up is added to the running one
+    Extend,
+    /// Every pickup is applied on its own and runs out independently
+    Stack,
+}
+
+impl Stacking {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "refresh" => Some(Stacking::Refresh),
+            "extend" => Some(Stacking::Extend),
+            "stack" => Some(Stacking::Stack),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PowerUpDefinition {
+    pub r#type: PowerUpType,
+    // shown to the player when the power-up is picked up
+    pub label: String,
+    pub sprite_id: String,
+    pub color: glm::Vec3,
+    pub duration: f32,
+    pub weight: u32,
+    pub stacking: Stacking,
 }
 
 #[derive(Debug, Clone)]
@@ -20,7 +87,12 @@ pub struct PowerUp {
     pub object: GameObject,
     pub r#type: PowerUpType,
     pub duration: f32,
+    // full lifetime of the power-up including any extensions, used to show its progress
+    pub total_duration: f32,
     pub activated: bool,
+    // how much the effect actually changed when activated, which can be less than
+    // asked for because of caps, so that it can be undone exactly on expiry
+    pub applied: f32,
 }
 
 impl PowerUp {
@@ -35,7 +107,359 @@ impl PowerUp {
             object: GameObject::new(position, POWERUP_SIZE, color, POWERUP_VELOCITY, sprite_id),
             r#type,
             duration,
+            total_duration: duration,
             activated: false,
+            applied: 0.0,
+        }
+    }
+
+    pub fn from_definition(definition: &PowerUpDefinition, position: glm::Vec2) -> Self {
+        Self::new(
+            definition.r#type,
+            definition.color,
+            definition.duration,
+            position,
+            definition.sprite_id.clone(),
+        )
+    }
+}
+
+/// Holds every power-up definition loaded from a definition file, together with the weight
+/// of not dropping anything at all.
+#[derive(Debug)]
+pub struct PowerUpRegistry {
+    definitions: Vec<PowerUpDefinition>,
+    none_weight: u32,
+}
+
+impl PowerUpRegistry {
+    pub fn new() -> Self {
+        Self {
+            definitions: vec![],
+            none_weight: 0,
+        }
+    }
+
+    /// Loads power-up definitions from a file made up of sections like
+    ///
+    /// ```text
+    /// [sticky]
+    /// label = STICKY
+    /// sprite = powerup_sticky
+    /// color = 1.0 0.5 1.0
+    /// duration = 20.0
+    /// weight = 1
+    /// stacking = stack
+    /// ```
+    ///
+    /// The special `[none]` section only takes a `weight`, which is the chance of a
+    /// destroyed brick not dropping anything.
+    pub fn load(&mut self, file: &Path) {
+        self.definitions.clear();
+        self.none_weight = 0;
+
+        let contents =
+            fs::read_to_string(file).expect("Should have been able to read the power-up file");
+        for (name, properties) in parse_sections(&contents) {
+            if name == "none" {
+                self.none_weight = parse_weight(&name, &properties).unwrap_or(0);
+                continue;
+            }
+
+            let Some(r#type) = PowerUpType::from_name(&name) else {
+                eprintln!("ERROR::POWERUP: Unknown power-up type {}", name);
+                continue;
+            };
+            match parse_definition(r#type, &name, &properties) {
+                Some(definition) => self.definitions.push(definition),
+                None => eprintln!("ERROR::POWERUP: Incomplete definition for {}", name),
+            }
+        }
+    }
+
+    pub fn get_definition(&self, r#type: PowerUpType) -> Option<&PowerUpDefinition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.r#type == r#type)
+    }
+
+    /// Picks at most one power-up from the weighted table. `overrides` replaces the
+    /// weight of the given types, which lets a level tune its own drop rates.
+    pub fn pick<R: Rng>(
+        &self,
+        overrides: &HashMap<PowerUpType, u32>,
+        rng: &mut R,
+    ) -> Option<&PowerUpDefinition> {
+        let weight_of = |definition: &PowerUpDefinition| {
+            *overrides
+                .get(&definition.r#type)
+                .unwrap_or(&definition.weight)
+        };
+
+        let total: u32 = self.none_weight + self.definitions.iter().map(weight_of).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0..total);
+        if roll < self.none_weight {
+            return None;
+        }
+        roll -= self.none_weight;
+
+        for definition in &self.definitions {
+            let weight = weight_of(definition);
+            if roll < weight {
+                return Some(definition);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+/// Loads per-type weight overrides from a file using the same section format as the
+/// power-up definitions, where only the `weight` property is read.
+pub fn load_weight_overrides(file: &Path) -> HashMap<PowerUpType, u32> {
+    let mut overrides = HashMap::new();
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(_) => return overrides,
+    };
+
+    for (name, properties) in parse_sections(&contents) {
+        match (
+            PowerUpType::from_name(&name),
+            parse_weight(&name, &properties),
+        ) {
+            (Some(r#type), Some(weight)) => {
+                overrides.insert(r#type, weight);
+            }
+            _ => eprintln!("ERROR::POWERUP: Invalid weight override for {}", name),
+        }
+    }
+    overrides
+}
+
+fn parse_definition(
+    r#type: PowerUpType,
+    name: &str,
+    properties: &Properties,
+) -> Option<PowerUpDefinition> {
+    Some(PowerUpDefinition {
+        r#type,
+        label: properties
+            .get("label")
+            .cloned()
+            .unwrap_or_else(|| name.to_uppercase()),
+        sprite_id: properties.get("sprite")?.clone(),
+        color: parse_vec3(properties.get("color")?)?,
+        duration: properties.get("duration")?.parse().ok()?,
+        weight: parse_weight(name, properties)?,
+        stacking: match properties.get("stacking") {
+            Some(stacking) => Stacking::from_name(stacking)?,
+            None => Stacking::Stack,
+        },
+    })
+}
+
+fn parse_weight(name: &str, properties: &Properties) -> Option<u32> {
+    let weight = properties.get("weight")?;
+    match weight.parse() {
+        Ok(weight) => Some(weight),
+        Err(_) => {
+            eprintln!("ERROR::POWERUP: Invalid weight {} for {}", weight, name);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // definition file written to the temp directory, removed again when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "breakout_powerup_{}_{}.def",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn definition(r#type: PowerUpType, weight: u32) -> PowerUpDefinition {
+        PowerUpDefinition {
+            r#type,
+            label: String::new(),
+            sprite_id: String::new(),
+            color: glm::vec3(1.0, 1.0, 1.0),
+            duration: 0.0,
+            weight,
+            stacking: Stacking::Stack,
         }
     }
+
+    fn registry(none_weight: u32, definitions: Vec<PowerUpDefinition>) -> PowerUpRegistry {
+        PowerUpRegistry {
+            definitions,
+            none_weight,
+        }
+    }
+
+    // types picked over many rolls, None for rolls that dropped nothing
+    fn picks(
+        registry: &PowerUpRegistry,
+        overrides: &HashMap<PowerUpType, u32>,
+    ) -> HashMap<Option<PowerUpType>, u32> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = HashMap::new();
+        for _ in 0..1000 {
+            let picked = registry
+                .pick(overrides, &mut rng)
+                .map(|definition| definition.r#type);
+            *counts.entry(picked).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn never_picks_types_with_zero_weight() {
+        let registry = registry(
+            0,
+            vec![
+                definition(PowerUpType::Speed, 0),
+                definition(PowerUpType::Sticky, 1),
+                definition(PowerUpType::Chaos, 0),
+            ],
+        );
+        let counts = picks(&registry, &HashMap::new());
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&Some(PowerUpType::Sticky)], 1000);
+    }
+
+    #[test]
+    fn picks_nothing_without_any_weight() {
+        let empty = registry(0, vec![definition(PowerUpType::Speed, 0)]);
+        assert_eq!(picks(&empty, &HashMap::new())[&None], 1000);
+
+        let only_none = registry(5, vec![definition(PowerUpType::Speed, 0)]);
+        assert_eq!(picks(&only_none, &HashMap::new())[&None], 1000);
+    }
+
+    #[test]
+    fn overrides_replace_the_weights() {
+        let registry = registry(
+            0,
+            vec![
+                definition(PowerUpType::Speed, 0),
+                definition(PowerUpType::Sticky, 10),
+            ],
+        );
+        let overrides = HashMap::from([(PowerUpType::Speed, 3), (PowerUpType::Sticky, 0)]);
+        let counts = picks(&registry, &overrides);
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&Some(PowerUpType::Speed)], 1000);
+    }
+
+    #[test]
+    fn picks_roughly_in_proportion_to_the_weights() {
+        let registry = registry(
+            2,
+            vec![
+                definition(PowerUpType::Speed, 1),
+                definition(PowerUpType::Sticky, 1),
+            ],
+        );
+        let counts = picks(&registry, &HashMap::new());
+        // expected 500, 250 and 250
+        assert!((400..600).contains(&counts[&None]));
+        assert!((175..325).contains(&counts[&Some(PowerUpType::Speed)]));
+        assert!((175..325).contains(&counts[&Some(PowerUpType::Sticky)]));
+    }
+
+    #[test]
+    fn loads_complete_definitions_only() {
+        let file = TempFile::new(
+            "definitions",
+            "[none]\n\
+             weight = 4\n\
+             [speed]\n\
+             sprite = powerup_speed\n\
+             color = 0.5 0.5 1.0\n\
+             duration = 10\n\
+             weight = 2\n\
+             unknown_key = ignored\n\
+             [sticky]\n\
+             sprite = powerup_sticky\n\
+             color = 1.0 0.5\n\
+             duration = 20\n\
+             weight = 1\n\
+             [teleport]\n\
+             weight = 1\n\
+             [chaos]\n\
+             label = CHAOS!\n\
+             sprite = powerup_chaos\n\
+             color = 0.9 0.25 0.25\n\
+             duration = 15\n\
+             weight = 1\n\
+             stacking = refresh\n",
+        );
+        let mut registry = PowerUpRegistry::new();
+        registry.load(&file.0);
+
+        assert_eq!(registry.none_weight, 4);
+        // sticky has a broken color and teleport is not a power-up
+        assert_eq!(registry.definitions.len(), 2);
+        let speed = registry.get_definition(PowerUpType::Speed).unwrap();
+        assert_eq!(speed.label, "SPEED");
+        assert_eq!(speed.weight, 2);
+        assert_eq!(speed.stacking, Stacking::Stack);
+        let chaos = registry.get_definition(PowerUpType::Chaos).unwrap();
+        assert_eq!(chaos.label, "CHAOS!");
+        assert_eq!(chaos.stacking, Stacking::Refresh);
+    }
+
+    #[test]
+    fn loads_valid_weight_overrides_only() {
+        let file = TempFile::new(
+            "overrides",
+            "[speed]\n\
+             weight = 5\n\
+             [sticky]\n\
+             weight = lots\n\
+             [chaos]\n\
+             label = no weight\n\
+             [teleport]\n\
+             weight = 1\n\
+             [laser]\n\
+             weight = 0\n",
+        );
+        let overrides = load_weight_overrides(&file.0);
+        assert_eq!(
+            overrides,
+            HashMap::from([(PowerUpType::Speed, 5), (PowerUpType::Laser, 0)])
+        );
+    }
+
+    #[test]
+    fn levels_without_overrides_keep_the_default_weights() {
+        let overrides = load_weight_overrides(Path::new("does/not/exist.def"));
+        assert!(overrides.is_empty());
+    }
 }
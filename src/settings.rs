@@ -0,0 +1,333 @@
+#![allow(dead_code)]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::definitions::{parse_sections, Properties};
+use crate::graphics::{bloom, post_processor::PostProcessor};
+
+#[derive(Debug, Clone)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    pub threshold: f32,
+    pub intensity: f32,
+    pub blur_passes: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: bloom::DEFAULT_THRESHOLD,
+            intensity: bloom::DEFAULT_INTENSITY,
+            blur_passes: bloom::DEFAULT_BLUR_PASSES,
+        }
+    }
+}
+
+/// Strengths of the parts of the CRT filter, 0 turns a part off
+#[derive(Debug, Clone)]
+pub struct CrtSettings {
+    pub enabled: bool,
+    pub scanlines: f32,
+    pub curvature: f32,
+    // distance in pixels the red and blue channels are moved apart at the edges
+    pub chromatic_aberration: f32,
+    pub phosphor_mask: f32,
+    pub vignette: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanlines: 0.35,
+            curvature: 0.08,
+            chromatic_aberration: 1.5,
+            phosphor_mask: 0.2,
+            vignette: 0.4,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScreenshotSettings {
+    pub directory: PathBuf,
+    // whether screenshots show the final frame or the scene before post processing
+    pub post_processing: bool,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("screenshots"),
+            post_processing: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingSettings {
+    // frames per second of the recording, the game runs at exactly this rate while recording
+    pub fps: u32,
+    // `frames` for numbered PNGs or `encoder` to send the frames to the encoder command
+    pub output: String,
+    pub directory: PathBuf,
+    // command reading raw RGBA frames from its standard input, `{width}`, `{height}`, `{fps}`,
+    // `{directory}` and `{timestamp}` are replaced by the values of the recording
+    pub encoder: String,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self {
+            fps: 60,
+            output: "frames".to_string(),
+            directory: PathBuf::from("recordings"),
+            encoder: "ffmpeg -y -f rawvideo -pixel_format rgba -video_size {width}x{height} \
+                      -framerate {fps} -i - -pix_fmt yuv420p {directory}/{timestamp}.mp4"
+                .to_string(),
+        }
+    }
+}
+
+/// Player settings read from a definition file, anything missing from it keeps its default
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub bloom: BloomSettings,
+    pub crt: CrtSettings,
+    pub screenshot: ScreenshotSettings,
+    pub recording: RecordingSettings,
+}
+
+impl Settings {
+    /// Reads the settings from a file, using the defaults when there is no file
+    pub fn load(file: &Path) -> Self {
+        let mut settings = Self::default();
+        let Ok(contents) = fs::read_to_string(file) else {
+            return settings;
+        };
+
+        for (name, properties) in parse_sections(&contents) {
+            match name.as_str() {
+                "bloom" => {
+                    let bloom = &mut settings.bloom;
+                    read(&name, &properties, "enabled", &mut bloom.enabled);
+                    read(&name, &properties, "threshold", &mut bloom.threshold);
+                    read(&name, &properties, "intensity", &mut bloom.intensity);
+                    read(&name, &properties, "blur_passes", &mut bloom.blur_passes);
+                }
+                "crt" => {
+                    let crt = &mut settings.crt;
+                    read(&name, &properties, "enabled", &mut crt.enabled);
+                    read(&name, &properties, "scanlines", &mut crt.scanlines);
+                    read(&name, &properties, "curvature", &mut crt.curvature);
+                    read(
+                        &name,
+                        &properties,
+                        "chromatic_aberration",
+                        &mut crt.chromatic_aberration,
+                    );
+                    read(&name, &properties, "phosphor_mask", &mut crt.phosphor_mask);
+                    read(&name, &properties, "vignette", &mut crt.vignette);
+                }
+                "screenshot" => {
+                    let screenshot = &mut settings.screenshot;
+                    read(&name, &properties, "directory", &mut screenshot.directory);
+                    read(
+                        &name,
+                        &properties,
+                        "post_processing",
+                        &mut screenshot.post_processing,
+                    );
+                }
+                "recording" => {
+                    let recording = &mut settings.recording;
+                    read(&name, &properties, "fps", &mut recording.fps);
+                    read(&name, &properties, "output", &mut recording.output);
+                    read(&name, &properties, "directory", &mut recording.directory);
+                    read(&name, &properties, "encoder", &mut recording.encoder);
+                }
+                _ => eprintln!("ERROR::SETTINGS: Unknown section {}", name),
+            }
+        }
+        settings
+    }
+
+    /// Sets up bloom and the CRT filter of the post processor
+    pub fn apply(&self, effects: &mut PostProcessor) {
+        if let Some(bloom) = &mut effects.bloom {
+            bloom.threshold = self.bloom.threshold;
+            bloom.intensity = if self.bloom.enabled {
+                self.bloom.intensity
+            } else {
+                0.0
+            };
+            bloom.blur_passes = self.bloom.blur_passes;
+        }
+
+        if let Some(crt) = effects.effect_mut("crt") {
+            let shader = crt.parameters();
+            shader.set_float("scanlines", self.crt.scanlines);
+            shader.set_float("curvature", self.crt.curvature);
+            shader.set_float("aberration", self.crt.chromatic_aberration);
+            shader.set_float("phosphorMask", self.crt.phosphor_mask);
+            shader.set_float("vignette", self.crt.vignette);
+            crt.set_enabled(self.crt.enabled);
+        }
+    }
+}
+
+/// Overwrites `value` with the property `key` when it is there and valid
+fn read<T: FromStr>(section: &str, properties: &Properties, key: &str, value: &mut T) {
+    let Some(property) = properties.get(key) else {
+        return;
+    };
+    match property.parse() {
+        Ok(parsed) => *value = parsed,
+        Err(_) => eprintln!(
+            "ERROR::SETTINGS: Invalid value for {} in [{}]: {}",
+            key, section, property
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // settings file written to the temp directory, removed again when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "breakout_settings_{}_{}.def",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn maps_every_key_to_its_setting() {
+        let file = TempFile::new(
+            "keys",
+            "[bloom]\n\
+             enabled = false\n\
+             threshold = 0.6\n\
+             intensity = 1.25\n\
+             blur_passes = 5\n\
+             [crt]\n\
+             enabled = true\n\
+             scanlines = 0.5\n\
+             curvature = 0.1\n\
+             chromatic_aberration = 2.0\n\
+             phosphor_mask = 0.3\n\
+             vignette = 0.25\n\
+             [screenshot]\n\
+             directory = captures/shots\n\
+             post_processing = false\n\
+             [recording]\n\
+             fps = 30\n\
+             output = encoder\n\
+             directory = captures/videos\n\
+             encoder = cat > {directory}/{timestamp}.rgba\n",
+        );
+        let settings = Settings::load(&file.0);
+
+        assert!(!settings.bloom.enabled);
+        assert_eq!(settings.bloom.threshold, 0.6);
+        assert_eq!(settings.bloom.intensity, 1.25);
+        assert_eq!(settings.bloom.blur_passes, 5);
+
+        assert!(settings.crt.enabled);
+        assert_eq!(settings.crt.scanlines, 0.5);
+        assert_eq!(settings.crt.curvature, 0.1);
+        assert_eq!(settings.crt.chromatic_aberration, 2.0);
+        assert_eq!(settings.crt.phosphor_mask, 0.3);
+        assert_eq!(settings.crt.vignette, 0.25);
+
+        assert_eq!(
+            settings.screenshot.directory,
+            PathBuf::from("captures/shots")
+        );
+        assert!(!settings.screenshot.post_processing);
+
+        assert_eq!(settings.recording.fps, 30);
+        assert_eq!(settings.recording.output, "encoder");
+        assert_eq!(
+            settings.recording.directory,
+            PathBuf::from("captures/videos")
+        );
+        assert_eq!(
+            settings.recording.encoder,
+            "cat > {directory}/{timestamp}.rgba"
+        );
+    }
+
+    #[test]
+    fn bad_values_keep_their_defaults() {
+        let file = TempFile::new(
+            "bad",
+            "[bloom]\n\
+             enabled = maybe\n\
+             threshold = bright\n\
+             blur_passes = -2\n\
+             intensity = 0.5\n\
+             [crt]\n\
+             scanlines =\n\
+             vignette = 0.1\n\
+             [screenshot]\n\
+             post_processing = sometimes\n\
+             [recording]\n\
+             fps = 29.97\n\
+             [audio]\n\
+             volume = 11\n",
+        );
+        let settings = Settings::load(&file.0);
+        let defaults = Settings::default();
+
+        assert_eq!(settings.bloom.enabled, defaults.bloom.enabled);
+        assert_eq!(settings.bloom.threshold, defaults.bloom.threshold);
+        assert_eq!(settings.bloom.blur_passes, defaults.bloom.blur_passes);
+        assert_eq!(settings.crt.scanlines, defaults.crt.scanlines);
+        assert_eq!(
+            settings.screenshot.post_processing,
+            defaults.screenshot.post_processing
+        );
+        assert_eq!(settings.recording.fps, defaults.recording.fps);
+        // the valid values next to them are still read
+        assert_eq!(settings.bloom.intensity, 0.5);
+        assert_eq!(settings.crt.vignette, 0.1);
+    }
+
+    #[test]
+    fn missing_file_gives_the_defaults() {
+        let settings = Settings::load(Path::new("resources/no_such_settings.def"));
+        let defaults = Settings::default();
+
+        assert_eq!(settings.bloom.enabled, defaults.bloom.enabled);
+        assert_eq!(settings.bloom.threshold, defaults.bloom.threshold);
+        assert_eq!(settings.bloom.intensity, defaults.bloom.intensity);
+        assert_eq!(settings.bloom.blur_passes, defaults.bloom.blur_passes);
+        assert_eq!(settings.crt.enabled, defaults.crt.enabled);
+        assert_eq!(settings.crt.scanlines, defaults.crt.scanlines);
+        assert_eq!(settings.crt.vignette, defaults.crt.vignette);
+        assert_eq!(settings.screenshot.directory, defaults.screenshot.directory);
+        assert_eq!(settings.recording.output, defaults.recording.output);
+        assert_eq!(settings.recording.encoder, defaults.recording.encoder);
+    }
+}
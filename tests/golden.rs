@@ -0,0 +1,173 @@
+//! Renders deterministic scenes offscreen and compares them with the reference images in
+//! `tests/golden`. It needs an OpenGL 3.3 context and is skipped, with a message saying
+//! so, on machines where none can be created.
+//!
+//! The scenes are rendered at a quarter of the window size, which is enough to catch
+//! rendering changes while keeping the reference images small.
+//!
+//! A scene without a reference image fails. Setting `UPDATE_GOLDEN=1` writes the rendered
+//! images as the new references, after adding a scene or an intended change to the
+//! rendering. When a scene does not match, the rendered image and a diff are written to
+//! `target/golden`.
+
+use std::{env, fs, io::BufWriter, path::PathBuf};
+
+use breakout::{
+    game::Game,
+    graphics::{
+        headless::HeadlessContext, shader_manager::ShaderManager, texture_manager::TextureManager,
+        Graphics,
+    },
+    settings::Settings,
+};
+use image::{
+    codecs::png::{CompressionType, FilterType, PngEncoder},
+    DynamicImage, ImageEncoder, Rgba, RgbaImage,
+};
+
+const WIDTH: u32 = 400;
+const HEIGHT: u32 = 300;
+// drivers round blending and filtering a little differently, a channel may be off by this
+// much before the pixel counts as different
+const CHANNEL_TOLERANCE: u8 = 8;
+// fraction of the pixels that may be different before a scene fails
+const MAX_DIFFERENT_PIXELS: f64 = 0.002;
+
+#[test]
+fn golden_images() {
+    let context = match HeadlessContext::new(WIDTH, HEIGHT) {
+        Ok(context) => context,
+        Err(error) => {
+            eprintln!(
+                "Skipping the golden image test, no OpenGL 3.3 context: {}",
+                error
+            );
+            return;
+        }
+    };
+
+    let graphics = Graphics::new(WIDTH, HEIGHT, ShaderManager::new(), TextureManager::new());
+    let mut game = Game::new(graphics);
+    game.init();
+    // the defaults rather than the player's settings file
+    game.apply_settings(Settings::default());
+    game.effects_mut().set_output(context.framebuffer());
+
+    let mut failures = vec![];
+    let mut check = |game: &mut Game, name: &str| {
+        context.clear();
+        game.render();
+        if let Err(failure) = compare(name, &context.read_pixels()) {
+            failures.push(failure);
+        }
+    };
+
+    // the game starts in the menu
+    check(&mut game, "menu");
+
+    for level in 0..game.level_count() as u32 {
+        game.start_level(level);
+        check(&mut game, &format!("level_{}", level + 1));
+    }
+
+    game.start_level(0);
+    for effect in ["chaos", "confuse", "shake"] {
+        game.effects_mut().set_enabled(effect, true);
+        check(&mut game, &format!("effect_{}", effect));
+        game.effects_mut().set_enabled(effect, false);
+    }
+
+    let mut settings = Settings::default();
+    settings.crt.enabled = true;
+    game.apply_settings(settings);
+    check(&mut game, "effect_crt");
+
+    let mut settings = Settings::default();
+    settings.bloom.enabled = false;
+    game.apply_settings(settings);
+    check(&mut game, "no_bloom");
+
+    game.clear();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Compares a rendered scene with its reference image, or writes the reference when
+/// references are being updated
+fn compare(name: &str, image: &RgbaImage) -> Result<(), String> {
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let reference_file = manifest.join("tests/golden").join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(reference_file.parent().unwrap()).unwrap();
+        save_reference(image, &reference_file);
+        println!("Wrote reference image {}", reference_file.display());
+        return Ok(());
+    }
+    if !reference_file.exists() {
+        return Err(format!(
+            "{}: there is no reference image {}, run with UPDATE_GOLDEN=1 to write it",
+            name,
+            reference_file.display()
+        ));
+    }
+
+    let reference = image::open(&reference_file)
+        .map_err(|error| format!("{}: failed to read reference image: {}", name, error))?
+        .to_rgba8();
+    if reference.dimensions() != image.dimensions() {
+        return Err(format!(
+            "{}: rendered {:?} but the reference is {:?}",
+            name,
+            image.dimensions(),
+            reference.dimensions()
+        ));
+    }
+
+    // different pixels are white in the diff, matching ones a dim copy of the reference
+    let mut diff = RgbaImage::new(image.width(), image.height());
+    let mut different = 0;
+    for ((actual, expected), marked) in image
+        .pixels()
+        .zip(reference.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let matches = actual
+            .0
+            .iter()
+            .zip(expected.0)
+            .all(|(a, e)| a.abs_diff(e) <= CHANNEL_TOLERANCE);
+        *marked = if matches {
+            Rgba([expected[0] / 4, expected[1] / 4, expected[2] / 4, 255])
+        } else {
+            different += 1;
+            Rgba([255, 255, 255, 255])
+        };
+    }
+
+    let fraction = different as f64 / (image.width() * image.height()) as f64;
+    if fraction <= MAX_DIFFERENT_PIXELS {
+        return Ok(());
+    }
+
+    let output = manifest.join("target/golden");
+    fs::create_dir_all(&output).unwrap();
+    image.save(output.join(format!("{}.png", name))).unwrap();
+    diff.save(output.join(format!("{}_diff.png", name)))
+        .unwrap();
+    Err(format!(
+        "{}: {:.2}% of the pixels differ from the reference, see {}",
+        name,
+        fraction * 100.0,
+        output.display()
+    ))
+}
+
+/// Writes a reference image as compactly as possible, the scenes are opaque so the alpha
+/// channel is left out
+fn save_reference(image: &RgbaImage, file: &PathBuf) {
+    let rgb = DynamicImage::ImageRgba8(image.clone()).into_rgb8();
+    let writer = BufWriter::new(fs::File::create(file).unwrap());
+    PngEncoder::new_with_quality(writer, CompressionType::Best, FilterType::Adaptive)
+        .write_image(&rgb, rgb.width(), rgb.height(), image::ColorType::Rgb8)
+        .unwrap();
+}